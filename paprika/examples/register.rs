@@ -1,6 +1,6 @@
 use std::{
     collections::VecDeque,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
 };
 
 // use ::paprika;
@@ -67,28 +67,29 @@ impl paprika::PkSpiceManager for Manager {
 }
 
 fn main() {
-    let mut spice = PkSpice::<Manager>::new(std::ffi::OsStr::new("ngspice.dll")).unwrap();
+    // e.g. SHAREDSPICE_PATH=target/debug/libfakespice.so to run without ngspice
+    let path = locate_sharedspice(None).unwrap_or_else(|| std::path::PathBuf::from("ngspice.dll"));
+    let mut spice: PkSpice = PkSpice::new(path.as_os_str()).unwrap();
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
-    let manager = Arc::new(Manager::new(buf.clone()));
+    let manager = Arc::new(Mutex::new(Manager::new(buf.clone())));
 
-    spice.init(Some(manager)); // register
-    spice.command("source tran.cir"); // results pointer array starts at same address
-    spice.command("tran 10u 10m"); // ngspice recommends sending in control statements separately, not as part of netlist
+    spice.init_manager(manager); // register
+    spice.command("source tran.cir").unwrap(); // results pointer array starts at same address
+    spice.command("tran 10u 10m").unwrap(); // ngspice recommends sending in control statements separately, not as part of netlist
 
-    spice.init(None); // unregister
-    spice.command("echo echo command");
+    spice.init_none(); // unregister
+    spice.command("echo echo command").unwrap();
 
     let mut line = String::new();
     loop {
         line.clear();
         let _ = std::io::stdin().read_line(&mut line).unwrap();
-        match line.as_str().split_once("\r\n") {
-            Some(tup) => {
-                spice.command(tup.0);
-            }
-            None => {
-                spice.command(line.as_str());
-            } // this should only happen for blank inputs {println!("{:?}", line);},
+        let result = match line.as_str().split_once("\r\n") {
+            Some(tup) => spice.command(tup.0),
+            None => spice.command(line.as_str()), // this should only happen for blank inputs {println!("{:?}", line);},
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
         }
     }
 }
@@ -0,0 +1,1083 @@
+//! Fake sharedspice library
+//!
+//! Exports the functions of `sharedspice.h` and replays the callbacks ngspice would send, so that paprika and
+//! its users can be tested on a machine without ngspice. Nothing is simulated: the loaded circuit only supplies
+//! node names and the analysis, and every node gets a made up but deterministic waveform.
+//!
+//! Sources declared `external` get their value from the GetVSRCData/GetISRCData callbacks of `ngSpice_Init_Sync`
+//! at every transient point, and an external voltage source drives the first node of its line.
+//!
+//! Nodes in brackets on code model lines, e.g. `a1 [in] [out] inv`, are event nodes: registered with
+//! `ngSpice_Init_Evt`, they toggle between `0s` and `1s` at every point and can be read back with `ngGet_Evt_NodeInfo`.
+//!
+//! Commands understood: `echo`, `version`, `source`, `op`, `tran`, `ac`, `run`, `bg_run`, `bg_halt`, `bg_resume`,
+//! `codemodel` (ignored) and `quit`. Anything else is reported through SendChar as an error.
+//!
+//! Like ngspice, state is global: load a copy of the library (`PkSpice::new_copy`) for each instance in a process.
+
+use std::ffi::{c_char, c_double, c_int, c_short, c_void, CStr, CString};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// ngspice version reported by the `version` command
+pub const FAKE_VERSION: u32 = 40;
+
+type SendChar = unsafe extern "C" fn(*const c_char, c_int, *const c_void) -> c_int;
+type SendStat = unsafe extern "C" fn(*const c_char, c_int, *const c_void) -> c_int;
+type ControlledExit = unsafe extern "C" fn(c_int, bool, bool, c_int, *const c_void) -> c_int;
+type SendData = unsafe extern "C" fn(*const VecValuesAll, c_int, c_int, *const c_void) -> c_int;
+type SendInitData = unsafe extern "C" fn(*const VecInfoAll, c_int, *const c_void) -> c_int;
+type BGThreadRunning = unsafe extern "C" fn(bool, c_int, *const c_void) -> c_int;
+type GetVSRCData =
+    unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int;
+type GetISRCData =
+    unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int;
+type SendEvtData = unsafe extern "C" fn(
+    c_int,
+    c_double,
+    c_double,
+    *const c_char,
+    *const c_void,
+    c_int,
+    c_int,
+    c_int,
+    *const c_void,
+) -> c_int;
+type SendInitEvtData =
+    unsafe extern "C" fn(c_int, c_int, *const c_char, *const c_char, c_int, *const c_void) -> c_int;
+type GetSyncData = unsafe extern "C" fn(
+    c_double,
+    *mut c_double,
+    c_double,
+    c_int,
+    c_int,
+    c_int,
+    *const c_void,
+) -> c_int;
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct NgComplex {
+    cx_real: c_double,
+    cx_imag: c_double,
+}
+#[repr(C)]
+pub struct VectorInfo {
+    v_name: *const c_char,
+    v_type: c_int,
+    v_flags: c_short,
+    v_realdata: *const c_double,
+    v_compdata: *const NgComplex,
+    v_length: c_int,
+}
+#[repr(C)]
+pub struct VecValues {
+    name: *const c_char,
+    creal: c_double,
+    cimag: c_double,
+    is_scale: bool,
+    is_complex: bool,
+}
+#[repr(C)]
+pub struct VecValuesAll {
+    veccount: c_int,
+    vecindex: c_int,
+    vecsa: *const *const VecValues,
+}
+#[repr(C)]
+pub struct VecInfo {
+    number: c_int,
+    vecname: *const c_char,
+    is_real: bool,
+    pdvec: *const c_void,
+    pdvecscale: *const c_void,
+}
+#[repr(C)]
+pub struct EvtData {
+    dcop: c_int,
+    step: c_double,
+    node_value: *const c_char,
+}
+#[repr(C)]
+pub struct EvtSharedData {
+    evt_dect: *const *const EvtData,
+    num_steps: c_int,
+}
+#[repr(C)]
+pub struct VecInfoAll {
+    name: *const c_char,
+    title: *const c_char,
+    date: *const c_char,
+    type_: *const c_char,
+    veccount: c_int,
+    vecs: *const *const VecInfo,
+}
+
+/// vector types (enum simvar_type)
+const SV_TIME: c_int = 1;
+const SV_FREQUENCY: c_int = 2;
+const SV_VOLTAGE: c_int = 3;
+/// vector flags
+const VF_REAL: c_short = 1;
+const VF_COMPLEX: c_short = 2;
+
+/// callbacks registered with ngSpice_Init
+#[derive(Copy, Clone)]
+struct Callbacks {
+    send_char: Option<SendChar>,
+    send_stat: Option<SendStat>,
+    exit: Option<ControlledExit>,
+    send_data: Option<SendData>,
+    send_init: Option<SendInitData>,
+    bg_running: Option<BGThreadRunning>,
+    /// registered with ngSpice_Init_Sync
+    get_vsrc: Option<GetVSRCData>,
+    get_isrc: Option<GetISRCData>,
+    get_sync: Option<GetSyncData>,
+    /// registered with ngSpice_Init_Evt
+    send_evt: Option<SendEvtData>,
+    send_init_evt: Option<SendInitEvtData>,
+    user: *const c_void,
+    id: c_int,
+}
+// safety: user data is only passed back to the callbacks, which must accept calls from the background thread
+unsafe impl Send for Callbacks {}
+
+impl Callbacks {
+    fn char(&self, msg: &str) {
+        if let Some(f) = self.send_char {
+            let cmsg = CString::new(msg).unwrap_or_default();
+            unsafe { f(cmsg.as_ptr(), self.id, self.user) };
+        }
+    }
+    fn stat(&self, msg: &str) {
+        if let Some(f) = self.send_stat {
+            let cmsg = CString::new(msg).unwrap_or_default();
+            unsafe { f(cmsg.as_ptr(), self.id, self.user) };
+        }
+    }
+    fn bg(&self, is_fin: bool) {
+        if let Some(f) = self.bg_running {
+            unsafe { f(is_fin, self.id, self.user) };
+        }
+    }
+    /// sends SendInitEvtData for every event node
+    fn init_evt(&self, evt_nodes: &[String]) {
+        if let Some(f) = self.send_init_evt {
+            let udn = CString::new("d").unwrap_or_default();
+            for (i, name) in evt_nodes.iter().enumerate() {
+                let name = CString::new(name.as_str()).unwrap_or_default();
+                unsafe {
+                    f(
+                        i as c_int,
+                        evt_nodes.len() as c_int - 1,
+                        name.as_ptr(),
+                        udn.as_ptr(),
+                        self.id,
+                        self.user,
+                    )
+                };
+            }
+        }
+    }
+    /// sends SendEvtData with the value of the event node at index
+    fn evt(&self, index: usize, step: f64, value: &str, mode: c_int) {
+        if let Some(f) = self.send_evt {
+            let svalue = CString::new(value).unwrap_or_default();
+            let dvalue = if value.starts_with('1') { 1.0 } else { 0.0 };
+            unsafe {
+                f(
+                    index as c_int,
+                    step,
+                    dvalue,
+                    svalue.as_ptr(),
+                    std::ptr::null(),
+                    0,
+                    mode,
+                    self.id,
+                    self.user,
+                )
+            };
+        }
+    }
+    /// calls GetSyncData at time with the step size, then returns the value of every external source,
+    /// or None for sources without a callback
+    fn sync(&self, time: f64, step: f64, sources: &[External]) -> Vec<Option<f64>> {
+        if let Some(f) = self.get_sync {
+            let mut delta = step;
+            unsafe { f(time, &mut delta, step, 0, self.id, 0, self.user) };
+        }
+        sources
+            .iter()
+            .map(|src| {
+                let f = if src.is_current {
+                    self.get_isrc
+                } else {
+                    self.get_vsrc
+                }?;
+                let name = CString::new(src.name.as_str()).unwrap_or_default();
+                let mut value = 0.0;
+                unsafe { f(&mut value, time, name.as_ptr(), self.id, self.user) };
+                Some(value)
+            })
+            .collect()
+    }
+}
+
+/// source declared `external`, e.g. `V1 in 0 dc 0 external`
+#[derive(Clone, Debug)]
+struct External {
+    /// instance name, e.g. `v1`
+    name: String,
+    is_current: bool,
+    /// index into the node names of the first node of the source
+    node: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Analysis {
+    Op,
+    Tran { step: f64, stop: f64 },
+    Ac { freqs: Vec<f64> },
+}
+
+impl Analysis {
+    /// plot name prefix and scale vector name and type, if any
+    fn kind(&self) -> (&'static str, Option<(&'static str, c_int)>) {
+        match self {
+            Analysis::Op => ("op", None),
+            Analysis::Tran { .. } => ("tran", Some(("time", SV_TIME))),
+            Analysis::Ac { .. } => ("ac", Some(("frequency", SV_FREQUENCY))),
+        }
+    }
+    /// scale value of every point
+    fn points(&self) -> Vec<f64> {
+        match self {
+            Analysis::Op => vec![0.0],
+            Analysis::Tran { step, stop } => {
+                let n = ((stop / step).round() as usize).min(100_000);
+                (0..=n).map(|i| i as f64 * step).collect()
+            }
+            Analysis::Ac { freqs } => freqs.clone(),
+        }
+    }
+    /// analysis mode reported with event data
+    fn mode(&self) -> c_int {
+        match self {
+            Analysis::Op => 0,
+            Analysis::Tran { .. } => 2,
+            Analysis::Ac { .. } => 3,
+        }
+    }
+    /// made up value of the event node numbered k (from 1) at point i
+    fn evt_value(k: usize, i: usize) -> &'static str {
+        if (k + i).is_multiple_of(2) {
+            "1s"
+        } else {
+            "0s"
+        }
+    }
+    /// made up value of the node numbered k (from 1) at scale value x
+    fn value(&self, k: usize, x: f64) -> NgComplex {
+        let k = k as f64;
+        match self {
+            Analysis::Op => NgComplex {
+                cx_real: k,
+                cx_imag: 0.0,
+            },
+            Analysis::Tran { stop, .. } => NgComplex {
+                cx_real: k * x / stop,
+                cx_imag: 0.0,
+            },
+            // single pole lowpass at 1kHz
+            Analysis::Ac { .. } => {
+                let w = x / 1e3;
+                NgComplex {
+                    cx_real: k / (1.0 + w * w),
+                    cx_imag: -k * w / (1.0 + w * w),
+                }
+            }
+        }
+    }
+}
+
+struct Vector {
+    name: CString,
+    v_type: c_int,
+    complex: bool,
+    real: Vec<f64>,
+    comp: Vec<NgComplex>,
+}
+
+struct Plot {
+    name: CString,
+    vecs: Vec<Vector>,
+}
+
+struct State {
+    callbacks: Option<Callbacks>,
+    /// node names of the loaded circuit
+    nodes: Vec<String>,
+    /// external sources of the loaded circuit
+    externals: Vec<External>,
+    /// event node names of the loaded circuit
+    evt_nodes: Vec<String>,
+    /// (dcop, step, value) of every point of every event node, in order of evt_nodes
+    evt_values: Vec<Vec<(c_int, f64, CString)>>,
+    /// analysis of the loaded circuit, if any
+    analysis: Option<Analysis>,
+    plots: Vec<Plot>,
+    /// index of the next point of the last plot, where a halted simulation resumes
+    next_point: usize,
+    breakpoint: Option<f64>,
+    /// returned to the caller, valid until the next call returning names or vector info
+    ret_names: Vec<CString>,
+    ret_ptrs: Vec<*const c_char>,
+    ret_vector_info: Option<Box<VectorInfo>>,
+    ret_evt_data: Vec<EvtData>,
+    ret_evt_ptrs: Vec<*const EvtData>,
+    ret_evt_shared: Option<Box<EvtSharedData>>,
+}
+// safety: the pointers held point into State itself
+unsafe impl Send for State {}
+
+impl State {
+    const fn new() -> Self {
+        State {
+            callbacks: None,
+            nodes: Vec::new(),
+            externals: Vec::new(),
+            evt_nodes: Vec::new(),
+            evt_values: Vec::new(),
+            analysis: None,
+            plots: Vec::new(),
+            next_point: 0,
+            breakpoint: None,
+            ret_names: Vec::new(),
+            ret_ptrs: Vec::new(),
+            ret_vector_info: None,
+            ret_evt_data: Vec::new(),
+            ret_evt_ptrs: Vec::new(),
+            ret_evt_shared: None,
+        }
+    }
+    /// returns names as a null terminated array, valid until the next call
+    fn ret_array(&mut self, names: Vec<String>) -> *const *const c_char {
+        self.ret_names = names
+            .into_iter()
+            .map(|n| CString::new(n).unwrap_or_default())
+            .collect();
+        self.ret_ptrs = self.ret_names.iter().map(|n| n.as_ptr()).collect();
+        self.ret_ptrs.push(std::ptr::null());
+        self.ret_ptrs.as_ptr()
+    }
+}
+
+static STATE: Mutex<State> = Mutex::new(State::new());
+/// true while the background thread runs
+static RUNNING: AtomicBool = AtomicBool::new(false);
+/// set by bg_halt to stop the background thread
+static HALT: AtomicBool = AtomicBool::new(false);
+
+fn state() -> MutexGuard<'static, State> {
+    // a panicking callback must not disable the library
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// registered callbacks, or callbacks which drop everything
+fn callbacks() -> Callbacks {
+    state().callbacks.unwrap_or(Callbacks {
+        send_char: None,
+        send_stat: None,
+        exit: None,
+        send_data: None,
+        send_init: None,
+        bg_running: None,
+        get_vsrc: None,
+        get_isrc: None,
+        get_sync: None,
+        send_evt: None,
+        send_init_evt: None,
+        user: std::ptr::null(),
+        id: 0,
+    })
+}
+
+/// parses a spice number, e.g. `10u`
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s.to_ascii_lowercase();
+    let split = s
+        .find(|c: char| c.is_alphabetic() && c != 'e')
+        .unwrap_or(s.len());
+    let (num, suffix) = s.split_at(split);
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else if suffix.starts_with("mil") {
+        25.4e-6
+    } else {
+        match suffix.chars().next() {
+            None => 1.0,
+            Some('t') => 1e12,
+            Some('g') => 1e9,
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            Some('f') => 1e-15,
+            Some(_) => 1.0,
+        }
+    };
+    num.parse::<f64>().ok().map(|x| x * scale)
+}
+
+/// parses an analysis command, e.g. `tran 1u 1m` or `ac dec 10 1 1meg`
+fn parse_analysis(tokens: &[&str]) -> Option<Analysis> {
+    match tokens {
+        ["op", ..] => Some(Analysis::Op),
+        ["tran", step, stop, ..] => Some(Analysis::Tran {
+            step: parse_number(step).filter(|x| *x > 0.0)?,
+            stop: parse_number(stop).filter(|x| *x > 0.0)?,
+        }),
+        ["ac", scale, n, fstart, fstop, ..] => {
+            let n: usize = n.parse().ok().filter(|n| *n > 0)?;
+            let fstart = parse_number(fstart).filter(|x| *x > 0.0)?;
+            let fstop = parse_number(fstop).filter(|x| *x >= fstart)?;
+            let freqs = match *scale {
+                "lin" if n == 1 => vec![fstart],
+                "lin" => (0..n)
+                    .map(|i| fstart + (fstop - fstart) * i as f64 / (n - 1) as f64)
+                    .collect(),
+                "dec" | "oct" => {
+                    let base: f64 = if *scale == "dec" { 10.0 } else { 2.0 };
+                    let total = (n as f64 * (fstop / fstart).log(base)).ceil() as usize;
+                    (0..=total)
+                        .map(|i| fstart * base.powf(i as f64 / n as f64))
+                        .collect()
+                }
+                _ => return None,
+            };
+            Some(Analysis::Ac { freqs })
+        }
+        _ => None,
+    }
+}
+
+/// loads a circuit: node names from the device lines and the analysis from its dot command, if any
+fn load_circuit(lines: &[String]) {
+    let mut nodes: Vec<String> = vec![];
+    let mut externals = vec![];
+    let mut evt_nodes: Vec<String> = vec![];
+    let mut analysis = None;
+    // the first line is the title
+    for line in lines.iter().skip(1) {
+        let line = line.trim().to_ascii_lowercase();
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let first = match tokens.first() {
+            Some(first) => *first,
+            None => continue,
+        };
+        if first == ".end" {
+            break;
+        }
+        if let Some(cmd) = first.strip_prefix('.') {
+            let mut tokens = tokens.clone();
+            tokens[0] = cmd;
+            if let Some(a) = parse_analysis(&tokens) {
+                analysis = Some(a);
+            }
+            continue;
+        }
+        if first.starts_with('*') || first.starts_with('+') {
+            continue;
+        }
+        if first.starts_with('a') {
+            for node in tokens.iter().skip(1) {
+                if let Some(node) = node.strip_prefix('[').and_then(|n| n.strip_suffix(']')) {
+                    if !evt_nodes.iter().any(|n| n == node) {
+                        evt_nodes.push(node.to_string());
+                    }
+                }
+            }
+            continue;
+        }
+        let port_count = match first.chars().next() {
+            Some('m') => 4,
+            Some('q') | Some('j') => 3,
+            Some('x') => tokens.len().saturating_sub(2),
+            _ => 2,
+        };
+        for node in tokens.iter().skip(1).take(port_count) {
+            if *node != "0" && *node != "gnd" && !nodes.iter().any(|n| n == node) {
+                nodes.push(node.to_string());
+            }
+        }
+        if matches!(first.chars().next(), Some('v') | Some('i')) && tokens.contains(&"external") {
+            externals.push(External {
+                name: first.to_string(),
+                is_current: first.starts_with('i'),
+                node: tokens
+                    .get(1)
+                    .and_then(|node| nodes.iter().position(|n| n == node)),
+            });
+        }
+    }
+    let mut st = state();
+    st.nodes = nodes;
+    st.externals = externals;
+    st.evt_values = vec![vec![]; evt_nodes.len()];
+    st.evt_nodes = evt_nodes;
+    st.analysis = analysis;
+    st.breakpoint = None;
+}
+
+/// replays the callbacks of a simulation of the loaded circuit, continuing the last plot if resume
+fn simulate(resume: bool) {
+    let cb = callbacks();
+    let (analysis, nodes, externals, evt_nodes, start, plot_index) = {
+        let mut st = state();
+        let analysis = match st.analysis.clone() {
+            Some(analysis) => analysis,
+            None => {
+                drop(st);
+                cb.char("stderr Error: no circuit loaded");
+                return;
+            }
+        };
+        let nodes = st.nodes.clone();
+        let externals = st.externals.clone();
+        let evt_nodes = st.evt_nodes.clone();
+        if !resume || st.plots.is_empty() {
+            let (prefix, scale) = analysis.kind();
+            let count = st
+                .plots
+                .iter()
+                .filter(|p| p.name.to_string_lossy().starts_with(prefix))
+                .count();
+            let complex = matches!(analysis, Analysis::Ac { .. });
+            let vecs = scale
+                .map(|(name, v_type)| (name.to_string(), v_type))
+                .into_iter()
+                .chain(nodes.iter().map(|n| (n.clone(), SV_VOLTAGE)))
+                .map(|(name, v_type)| Vector {
+                    name: CString::new(name).unwrap_or_default(),
+                    v_type,
+                    complex: complex && v_type == SV_VOLTAGE,
+                    real: vec![],
+                    comp: vec![],
+                })
+                .collect();
+            st.plots.push(Plot {
+                name: CString::new(format!("{}{}", prefix, count + 1)).unwrap_or_default(),
+                vecs,
+            });
+            st.next_point = 0;
+            st.evt_values = vec![vec![]; evt_nodes.len()];
+        }
+        (
+            analysis,
+            nodes,
+            externals,
+            evt_nodes,
+            st.next_point,
+            st.plots.len() - 1,
+        )
+    };
+    let (prefix, scale) = analysis.kind();
+    if start == 0 {
+        send_init(&cb, plot_index, scale.is_some());
+        cb.init_evt(&evt_nodes);
+    }
+    let points = analysis.points();
+    for (i, x) in points.iter().enumerate().skip(start) {
+        if HALT.swap(false, Ordering::SeqCst) {
+            state().next_point = i;
+            return;
+        }
+        let mut values: Vec<NgComplex> = (1..=nodes.len()).map(|k| analysis.value(k, *x)).collect();
+        if let Analysis::Tran { step, .. } = analysis {
+            for (src, value) in externals.iter().zip(cb.sync(*x, step, &externals)) {
+                if let (false, Some(node), Some(value)) = (src.is_current, src.node, value) {
+                    values[node] = NgComplex {
+                        cx_real: value,
+                        cx_imag: 0.0,
+                    };
+                }
+            }
+        }
+        {
+            let mut st = state();
+            let plot = &mut st.plots[plot_index];
+            let mut vecs = plot.vecs.iter_mut();
+            if scale.is_some() {
+                if let Some(v) = vecs.next() {
+                    v.real.push(*x);
+                }
+            }
+            for (v, value) in vecs.zip(&values) {
+                if v.complex {
+                    v.comp.push(*value);
+                } else {
+                    v.real.push(value.cx_real);
+                }
+            }
+            st.next_point = i + 1;
+        }
+        send_data(&cb, i, scale.map(|(name, _)| (name, *x)), &nodes, &values);
+        if !matches!(analysis, Analysis::Ac { .. }) {
+            for k in 0..evt_nodes.len() {
+                let value = Analysis::evt_value(k + 1, i);
+                state().evt_values[k].push((
+                    (i == 0) as c_int,
+                    *x,
+                    CString::new(value).unwrap_or_default(),
+                ));
+                cb.evt(k, *x, value, analysis.mode());
+            }
+        }
+        cb.stat(&format!(
+            "{}: {:.1}%",
+            prefix,
+            100.0 * (i + 1) as f64 / points.len() as f64
+        ));
+        // like ngspice, stop at the first point at or past the breakpoint, which is kept
+        let mut st = state();
+        match st.breakpoint {
+            Some(t) if matches!(analysis, Analysis::Tran { .. }) && *x >= t => {
+                st.breakpoint = None;
+                return;
+            }
+            _ => {}
+        }
+    }
+    cb.stat("--ready--");
+}
+
+/// sends the vectors of the plot at plot_index, the first of which is the scale if has_scale
+fn send_init(cb: &Callbacks, plot_index: usize, has_scale: bool) {
+    let f = match cb.send_init {
+        Some(f) => f,
+        None => return,
+    };
+    let (name, names, real) = {
+        let st = state();
+        let plot = &st.plots[plot_index];
+        let names: Vec<CString> = plot.vecs.iter().map(|v| v.name.clone()).collect();
+        let real: Vec<bool> = plot.vecs.iter().map(|v| !v.complex).collect();
+        (plot.name.clone(), names, real)
+    };
+    // the vector pointers only identify vectors, the name pointers serve as well
+    let scale = match (has_scale, names.first()) {
+        (true, Some(name)) => name.as_ptr() as *const c_void,
+        _ => std::ptr::null(),
+    };
+    let infos: Vec<VecInfo> = names
+        .iter()
+        .zip(real)
+        .enumerate()
+        .map(|(i, (name, is_real))| VecInfo {
+            number: i as c_int,
+            vecname: name.as_ptr(),
+            is_real,
+            pdvec: name.as_ptr() as *const c_void,
+            pdvecscale: scale,
+        })
+        .collect();
+    let ptrs: Vec<*const VecInfo> = infos.iter().map(|i| i as *const VecInfo).collect();
+    let title = CString::new("fakespice").unwrap_or_default();
+    let date = CString::new("").unwrap_or_default();
+    let all = VecInfoAll {
+        name: name.as_ptr(),
+        title: title.as_ptr(),
+        date: date.as_ptr(),
+        type_: name.as_ptr(),
+        veccount: ptrs.len() as c_int,
+        vecs: ptrs.as_ptr(),
+    };
+    unsafe { f(&all, cb.id, cb.user) };
+}
+
+fn send_data(
+    cb: &Callbacks,
+    index: usize,
+    scale: Option<(&str, f64)>,
+    nodes: &[String],
+    values: &[NgComplex],
+) {
+    let f = match cb.send_data {
+        Some(f) => f,
+        None => return,
+    };
+    let names: Vec<CString> = scale
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .chain(nodes.iter().cloned())
+        .map(|n| CString::new(n).unwrap_or_default())
+        .collect();
+    let complex = values.iter().any(|v| v.cx_imag != 0.0);
+    let vals: Vec<VecValues> = scale
+        .iter()
+        .map(|(_, x)| {
+            (
+                NgComplex {
+                    cx_real: *x,
+                    cx_imag: 0.0,
+                },
+                true,
+            )
+        })
+        .chain(values.iter().map(|v| (*v, false)))
+        .zip(&names)
+        .map(|((v, is_scale), name)| VecValues {
+            name: name.as_ptr(),
+            creal: v.cx_real,
+            cimag: v.cx_imag,
+            is_scale,
+            is_complex: complex && !is_scale,
+        })
+        .collect();
+    let ptrs: Vec<*const VecValues> = vals.iter().map(|v| v as *const VecValues).collect();
+    let all = VecValuesAll {
+        veccount: ptrs.len() as c_int,
+        vecindex: index as c_int,
+        vecsa: ptrs.as_ptr(),
+    };
+    unsafe { f(&all, ptrs.len() as c_int, cb.id, cb.user) };
+}
+
+/// replays a simulation in the background thread, bracketed by BGThreadRunning callbacks
+fn spawn_bg(resume: bool) -> c_int {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        callbacks().char("stderr Error: background thread is already running");
+        return 1;
+    }
+    HALT.store(false, Ordering::SeqCst);
+    std::thread::spawn(move || {
+        let cb = callbacks();
+        cb.bg(false);
+        simulate(resume);
+        RUNNING.store(false, Ordering::SeqCst);
+        cb.bg(true);
+    });
+    0
+}
+
+fn command(cmd: &str) -> c_int {
+    let cb = callbacks();
+    let tokens: Vec<&str> = cmd.split_whitespace().collect();
+    match tokens.as_slice() {
+        [] => 0,
+        ["echo", ..] => {
+            let text = cmd.trim_start().strip_prefix("echo").unwrap_or_default();
+            cb.char(&format!("stdout {}", text.trim()));
+            0
+        }
+        ["version", ..] => {
+            cb.char("stdout ******");
+            cb.char(&format!(
+                "stdout ** ngspice-{} : fakespice shared library",
+                FAKE_VERSION
+            ));
+            cb.char("stdout ******");
+            0
+        }
+        ["source", path] => match std::fs::read_to_string(path) {
+            Ok(s) => {
+                load_circuit(&s.lines().map(String::from).collect::<Vec<_>>());
+                0
+            }
+            Err(e) => {
+                cb.char(&format!("stderr Error: {}: {}", path, e));
+                1
+            }
+        },
+        ["run"] => {
+            simulate(false);
+            0
+        }
+        ["bg_run"] => spawn_bg(false),
+        ["bg_resume"] => spawn_bg(true),
+        ["bg_halt"] => {
+            HALT.store(true, Ordering::SeqCst);
+            while RUNNING.load(Ordering::SeqCst) {
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            HALT.store(false, Ordering::SeqCst);
+            0
+        }
+        ["codemodel", ..] => 0,
+        ["quit", ..] => {
+            if let Some(f) = cb.exit {
+                unsafe { f(0, false, true, cb.id, cb.user) };
+            }
+            0
+        }
+        _ => match parse_analysis(&tokens) {
+            Some(analysis) => {
+                state().analysis = Some(analysis);
+                simulate(false);
+                0
+            }
+            None => {
+                cb.char(&format!("stderr Error: unknown command {}", cmd));
+                1
+            }
+        },
+    }
+}
+
+/// # Safety
+/// userdata is passed back to the callbacks, from the calling thread and the background thread
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Init(
+    printfcn: Option<SendChar>,
+    statfcn: Option<SendStat>,
+    ngexit: Option<ControlledExit>,
+    datfcn: Option<SendData>,
+    datinitfcn: Option<SendInitData>,
+    bgtrun: Option<BGThreadRunning>,
+    userdata: *const c_void,
+) -> c_int {
+    let mut st = state();
+    // id and sync callbacks are set by ngSpice_Init_Sync, event callbacks by ngSpice_Init_Evt
+    let prev = st.callbacks;
+    st.callbacks = Some(Callbacks {
+        send_char: printfcn,
+        send_stat: statfcn,
+        exit: ngexit,
+        send_data: datfcn,
+        send_init: datinitfcn,
+        bg_running: bgtrun,
+        get_vsrc: prev.and_then(|cb| cb.get_vsrc),
+        get_isrc: prev.and_then(|cb| cb.get_isrc),
+        get_sync: prev.and_then(|cb| cb.get_sync),
+        send_evt: prev.and_then(|cb| cb.send_evt),
+        send_init_evt: prev.and_then(|cb| cb.send_init_evt),
+        user: userdata,
+        id: prev.map_or(0, |cb| cb.id),
+    });
+    0
+}
+
+/// # Safety
+/// ident must be null or point to a valid int
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Init_Sync(
+    vsrcdat: Option<GetVSRCData>,
+    isrcdat: Option<GetISRCData>,
+    syncdat: Option<GetSyncData>,
+    ident: *const c_int,
+    userdata: *const c_void,
+) -> c_int {
+    let mut st = state();
+    if let Some(cb) = st.callbacks.as_mut() {
+        cb.get_vsrc = vsrcdat;
+        cb.get_isrc = isrcdat;
+        cb.get_sync = syncdat;
+        if !ident.is_null() {
+            cb.id = *ident;
+        }
+        if !userdata.is_null() {
+            cb.user = userdata;
+        }
+    }
+    0
+}
+
+/// # Safety
+/// userdata is passed back to the callbacks, from the calling thread and the background thread
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Init_Evt(
+    evtfcn: Option<SendEvtData>,
+    evtinitfcn: Option<SendInitEvtData>,
+    userdata: *const c_void,
+) -> c_int {
+    let mut st = state();
+    match st.callbacks.as_mut() {
+        Some(cb) => {
+            cb.send_evt = evtfcn;
+            cb.send_init_evt = evtinitfcn;
+            if !userdata.is_null() {
+                cb.user = userdata;
+            }
+            0
+        }
+        // ngSpice_Init must be called first
+        None => 1,
+    }
+}
+
+/// # Safety
+/// nodename must be a valid null terminated string. The result is valid until the next call or simulation
+#[no_mangle]
+pub unsafe extern "C" fn ngGet_Evt_NodeInfo(nodename: *const c_char) -> *const EvtSharedData {
+    let nodename = CStr::from_ptr(nodename).to_string_lossy().into_owned();
+    // plotname.nodename names the node of the current plot as well
+    let name = nodename
+        .split_once('.')
+        .map_or(nodename.as_str(), |(_, name)| name);
+    let mut st = state();
+    let index = match st.evt_nodes.iter().position(|n| n == name) {
+        Some(index) => index,
+        None => return std::ptr::null(),
+    };
+    let data: Vec<EvtData> = st.evt_values[index]
+        .iter()
+        .map(|(dcop, step, value)| EvtData {
+            dcop: *dcop,
+            step: *step,
+            node_value: value.as_ptr(),
+        })
+        .collect();
+    st.ret_evt_data = data;
+    st.ret_evt_ptrs = st
+        .ret_evt_data
+        .iter()
+        .map(|d| d as *const EvtData)
+        .collect();
+    let shared = Box::new(EvtSharedData {
+        evt_dect: st.ret_evt_ptrs.as_ptr(),
+        num_steps: st.ret_evt_ptrs.len() as c_int,
+    });
+    let ptr: *const EvtSharedData = shared.as_ref();
+    st.ret_evt_shared = Some(shared);
+    ptr
+}
+
+/// The result is valid until the next call returning names
+#[no_mangle]
+pub extern "C" fn ngSpice_AllEvtNodes() -> *const *const c_char {
+    let mut st = state();
+    let names = st.evt_nodes.clone();
+    st.ret_array(names)
+}
+
+/// # Safety
+/// command must be null or a valid null terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Command(command_str: *const c_char) -> c_int {
+    if command_str.is_null() {
+        return 0;
+    }
+    command(&CStr::from_ptr(command_str).to_string_lossy())
+}
+
+/// # Safety
+/// circarray must be a null terminated array of valid null terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_Circ(circarray: *const *const c_char) -> c_int {
+    let mut lines = vec![];
+    let mut p = circarray;
+    while !(*p).is_null() {
+        lines.push(CStr::from_ptr(*p).to_string_lossy().into_owned());
+        p = p.add(1);
+    }
+    load_circuit(&lines);
+    0
+}
+
+/// # Safety
+/// vecname must be a valid null terminated string. The result is valid until the next call or simulation
+#[no_mangle]
+pub unsafe extern "C" fn ngGet_Vec_Info(vecname: *const c_char) -> *const VectorInfo {
+    let vecname = CStr::from_ptr(vecname).to_string_lossy().into_owned();
+    let mut st = state();
+    let (plot_name, name) = match vecname.split_once('.') {
+        Some((plot, name)) => (Some(plot.to_string()), name.to_string()),
+        None => (None, vecname),
+    };
+    let plot = match &plot_name {
+        Some(plot_name) => st
+            .plots
+            .iter()
+            .find(|p| p.name.to_string_lossy() == plot_name.as_str()),
+        None => st.plots.last(),
+    };
+    let info = match plot.and_then(|p| p.vecs.iter().find(|v| v.name.to_string_lossy() == name)) {
+        Some(v) => Box::new(VectorInfo {
+            v_name: v.name.as_ptr(),
+            v_type: v.v_type,
+            v_flags: if v.complex { VF_COMPLEX } else { VF_REAL },
+            v_realdata: if v.complex {
+                std::ptr::null()
+            } else {
+                v.real.as_ptr()
+            },
+            v_compdata: if v.complex {
+                v.comp.as_ptr()
+            } else {
+                std::ptr::null()
+            },
+            v_length: if v.complex {
+                v.comp.len()
+            } else {
+                v.real.len()
+            } as c_int,
+        }),
+        None => return std::ptr::null(),
+    };
+    let ptr: *const VectorInfo = info.as_ref();
+    st.ret_vector_info = Some(info);
+    ptr
+}
+
+/// The result is valid until the next call returning names
+#[no_mangle]
+pub extern "C" fn ngSpice_CurPlot() -> *const c_char {
+    let mut st = state();
+    let name = st.plots.last().map_or(String::from("const"), |p| {
+        p.name.to_string_lossy().into_owned()
+    });
+    st.ret_array(vec![name]);
+    st.ret_ptrs[0]
+}
+
+/// The result is valid until the next call returning names
+#[no_mangle]
+pub extern "C" fn ngSpice_AllPlots() -> *const *const c_char {
+    let mut st = state();
+    // newest first, as ngspice lists them
+    let mut names: Vec<String> = st
+        .plots
+        .iter()
+        .rev()
+        .map(|p| p.name.to_string_lossy().into_owned())
+        .collect();
+    names.push(String::from("const"));
+    st.ret_array(names)
+}
+
+/// # Safety
+/// plotname must be a valid null terminated string. The result is valid until the next call returning names
+#[no_mangle]
+pub unsafe extern "C" fn ngSpice_AllVecs(plotname: *const c_char) -> *const *const c_char {
+    let plotname = CStr::from_ptr(plotname).to_string_lossy().into_owned();
+    let mut st = state();
+    let names = st
+        .plots
+        .iter()
+        .find(|p| p.name.to_string_lossy() == plotname.as_str())
+        .map(|p| {
+            p.vecs
+                .iter()
+                .map(|v| v.name.to_string_lossy().into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    st.ret_array(names)
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+#[no_mangle]
+pub extern "C" fn ngSpice_SetBkpt(time: c_double) -> bool {
+    let mut st = state();
+    if st.analysis.is_none() {
+        return false;
+    }
+    st.breakpoint = Some(time);
+    true
+}
@@ -0,0 +1,466 @@
+//! Typed analysis commands
+//!
+//! `Analysis` validates its arguments and renders the ngspice command, e.g. `tran 1e-6 1e-3`,
+//! to be sent through `command`, or the dot command, e.g. `.tran 1e-6 1e-3`, to be placed in a netlist.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Point spacing of a frequency sweep
+pub enum SweepScale {
+    /// points per decade
+    Dec,
+    /// points per octave
+    Oct,
+    /// points in total, linearly spaced
+    Lin,
+}
+
+impl fmt::Display for SweepScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SweepScale::Dec => write!(f, "dec"),
+            SweepScale::Oct => write!(f, "oct"),
+            SweepScale::Lin => write!(f, "lin"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Frequency sweep shared by ac, noise, sens and disto analyses
+pub struct FreqSweep {
+    pub scale: SweepScale,
+    /// number of points, per decade/octave or in total depending on scale
+    pub points: u32,
+    /// start frequency (hertz), which may be zero for a linear sweep
+    pub fstart: f64,
+    /// stop frequency (hertz)
+    pub fstop: f64,
+}
+
+impl FreqSweep {
+    fn validate(&self) -> Result<(), AnalysisError> {
+        if self.points == 0 {
+            return Err(AnalysisError::ZeroPoints);
+        }
+        // logarithmic sweeps cannot start from zero, linear ones can
+        match self.scale {
+            SweepScale::Dec | SweepScale::Oct => positive("fstart", self.fstart)?,
+            SweepScale::Lin => non_negative("fstart", self.fstart)?,
+        }
+        positive("fstop", self.fstop)?;
+        ordered("fstart", self.fstart, "fstop", self.fstop)
+    }
+}
+
+impl fmt::Display for FreqSweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {:e} {:e}",
+            self.scale, self.points, self.fstart, self.fstop
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Sweep of source src from start to stop in increments of step, for dc analyses
+pub struct DcSweep {
+    /// independent voltage or current source, e.g. `v1`
+    pub src: String,
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64,
+}
+
+impl DcSweep {
+    fn validate(&self) -> Result<(), AnalysisError> {
+        dc_sweep(&self.src, self.start, self.stop, self.step)
+    }
+}
+
+fn dc_sweep(src: &str, start: f64, stop: f64, step: f64) -> Result<(), AnalysisError> {
+    named("src", src)?;
+    finite("start", start)?;
+    finite("stop", stop)?;
+    finite("step", step)?;
+    if step == 0.0 || (stop - start) * step < 0.0 {
+        return Err(AnalysisError::Step);
+    }
+    Ok(())
+}
+
+impl fmt::Display for DcSweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:e} {:e} {:e}",
+            self.src, self.start, self.stop, self.step
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Transfer function of a pole-zero analysis
+pub enum PzTransfer {
+    /// (output voltage)/(input current)
+    Cur,
+    /// (output voltage)/(input voltage)
+    Vol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What a pole-zero analysis solves for
+pub enum PzKind {
+    Poles,
+    Zeros,
+    Both,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Analysis as described in the Analyses and Output Control chapter of the Ngspice User's Manual
+pub enum Analysis {
+    /// operating point
+    Op,
+    /// dc transfer curve: sweep source src, repeating the sweep for every point of the nested sweep of a second source if some
+    Dc {
+        src: String,
+        start: f64,
+        stop: f64,
+        step: f64,
+        nested: Option<DcSweep>,
+    },
+    /// small-signal ac
+    Ac(FreqSweep),
+    /// transient, from 0 to tstop with printing increment tstep.
+    /// Data is stored from tstart, steps are limited to tmax. uic skips the operating point.
+    Tran {
+        tstep: f64,
+        tstop: f64,
+        tstart: Option<f64>,
+        tmax: Option<f64>,
+        uic: bool,
+    },
+    /// noise at node output (relative to ref_node, ground if none) with respect to input source src.
+    /// Summaries are printed every pts_per_summary points, if some.
+    Noise {
+        output: String,
+        ref_node: Option<String>,
+        src: String,
+        sweep: FreqSweep,
+        pts_per_summary: Option<u32>,
+    },
+    /// dc small-signal transfer function from input source src to output variable, e.g. `v(5,3)` or `i(vout)`
+    Tf { output: String, src: String },
+    /// dc sensitivity of output variable, or ac sensitivity over sweep if some
+    Sens {
+        output: String,
+        sweep: Option<FreqSweep>,
+    },
+    /// pole-zero analysis with input across nodes in_pos, in_neg and output across out_pos, out_neg
+    Pz {
+        in_pos: String,
+        in_neg: String,
+        out_pos: String,
+        out_neg: String,
+        transfer: PzTransfer,
+        kind: PzKind,
+    },
+    /// small-signal distortion, with the second frequency at f2overf1 times the sweep frequency, if some
+    Disto {
+        sweep: FreqSweep,
+        f2overf1: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Reason an `Analysis` is invalid
+pub enum AnalysisError {
+    /// named argument is NaN or infinite
+    NotFinite(&'static str),
+    /// named argument must be greater than zero
+    NotPositive(&'static str),
+    /// named argument must not be less than zero
+    Negative(&'static str),
+    /// first named argument must not be greater than the second
+    Order(&'static str, &'static str),
+    /// first named argument must be less than the second
+    NotLess(&'static str, &'static str),
+    /// sweep has no points
+    ZeroPoints,
+    /// dc sweep step is zero or points away from stop
+    Step,
+    /// named argument is out of the range stated
+    OutOfRange(&'static str, &'static str),
+    /// named node, source or output variable is empty
+    EmptyName(&'static str),
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::NotFinite(p) => write!(f, "{} is not a finite number", p),
+            AnalysisError::NotPositive(p) => write!(f, "{} must be greater than zero", p),
+            AnalysisError::Negative(p) => write!(f, "{} must not be negative", p),
+            AnalysisError::Order(a, b) => write!(f, "{} must not be greater than {}", a, b),
+            AnalysisError::NotLess(a, b) => write!(f, "{} must be less than {}", a, b),
+            AnalysisError::ZeroPoints => write!(f, "sweep must have at least one point"),
+            AnalysisError::Step => write!(f, "step must be nonzero and point from start to stop"),
+            AnalysisError::OutOfRange(p, range) => write!(f, "{} must be in {}", p, range),
+            AnalysisError::EmptyName(p) => write!(f, "{} must not be empty", p),
+        }
+    }
+}
+
+impl std::error::Error for AnalysisError {}
+
+fn finite(name: &'static str, x: f64) -> Result<(), AnalysisError> {
+    if x.is_finite() {
+        Ok(())
+    } else {
+        Err(AnalysisError::NotFinite(name))
+    }
+}
+
+fn positive(name: &'static str, x: f64) -> Result<(), AnalysisError> {
+    finite(name, x)?;
+    if x > 0.0 {
+        Ok(())
+    } else {
+        Err(AnalysisError::NotPositive(name))
+    }
+}
+
+fn non_negative(name: &'static str, x: f64) -> Result<(), AnalysisError> {
+    finite(name, x)?;
+    if x >= 0.0 {
+        Ok(())
+    } else {
+        Err(AnalysisError::Negative(name))
+    }
+}
+
+fn ordered(a: &'static str, x: f64, b: &'static str, y: f64) -> Result<(), AnalysisError> {
+    if x <= y {
+        Ok(())
+    } else {
+        Err(AnalysisError::Order(a, b))
+    }
+}
+
+fn named(name: &'static str, s: &str) -> Result<(), AnalysisError> {
+    if s.trim().is_empty() {
+        Err(AnalysisError::EmptyName(name))
+    } else {
+        Ok(())
+    }
+}
+
+impl Analysis {
+    /// Returns an error describing the first invalid argument, if any
+    pub fn validate(&self) -> Result<(), AnalysisError> {
+        match self {
+            Analysis::Op => Ok(()),
+            Analysis::Dc {
+                src,
+                start,
+                stop,
+                step,
+                nested,
+            } => {
+                dc_sweep(src, *start, *stop, *step)?;
+                match nested {
+                    Some(nested) => nested.validate(),
+                    None => Ok(()),
+                }
+            }
+            Analysis::Ac(sweep) => sweep.validate(),
+            Analysis::Tran {
+                tstep,
+                tstop,
+                tstart,
+                tmax,
+                uic: _,
+            } => {
+                positive("tstep", *tstep)?;
+                positive("tstop", *tstop)?;
+                ordered("tstep", *tstep, "tstop", *tstop)?;
+                if let Some(tstart) = tstart {
+                    non_negative("tstart", *tstart)?;
+                    if *tstart >= *tstop {
+                        return Err(AnalysisError::NotLess("tstart", "tstop"));
+                    }
+                }
+                if let Some(tmax) = tmax {
+                    positive("tmax", *tmax)?;
+                }
+                Ok(())
+            }
+            Analysis::Noise {
+                output,
+                ref_node,
+                src,
+                sweep,
+                pts_per_summary,
+            } => {
+                named("output", output)?;
+                if let Some(ref_node) = ref_node {
+                    named("ref_node", ref_node)?;
+                }
+                named("src", src)?;
+                sweep.validate()?;
+                if *pts_per_summary == Some(0) {
+                    return Err(AnalysisError::NotPositive("pts_per_summary"));
+                }
+                Ok(())
+            }
+            Analysis::Tf { output, src } => {
+                named("output", output)?;
+                named("src", src)
+            }
+            Analysis::Sens { output, sweep } => {
+                named("output", output)?;
+                match sweep {
+                    Some(sweep) => sweep.validate(),
+                    None => Ok(()),
+                }
+            }
+            Analysis::Pz {
+                in_pos,
+                in_neg,
+                out_pos,
+                out_neg,
+                ..
+            } => {
+                named("in_pos", in_pos)?;
+                named("in_neg", in_neg)?;
+                named("out_pos", out_pos)?;
+                named("out_neg", out_neg)
+            }
+            Analysis::Disto { sweep, f2overf1 } => {
+                sweep.validate()?;
+                if let Some(f2overf1) = f2overf1 {
+                    positive("f2overf1", *f2overf1)?;
+                    if *f2overf1 >= 1.0 {
+                        return Err(AnalysisError::OutOfRange("f2overf1", "(0, 1)"));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the ngspice command, e.g. `tran 1e-6 1e-3`, or an error if the analysis is invalid
+    pub fn command(&self) -> Result<String, AnalysisError> {
+        self.validate()?;
+        Ok(self.render())
+    }
+
+    /// Returns the dot command for a netlist, e.g. `.tran 1e-6 1e-3`, or an error if the analysis is invalid
+    pub fn card(&self) -> Result<String, AnalysisError> {
+        self.command().map(|c| format!(".{}", c))
+    }
+
+    /// Returns the number of points of each sweep of the scale, if the scale is swept repeatedly:
+    /// for a dc analysis with a nested sweep, src is swept again for every point of the nested source
+    pub fn sweep_points(&self) -> Option<usize> {
+        match self {
+            Analysis::Dc {
+                start,
+                stop,
+                step,
+                nested: Some(_),
+                ..
+            } => Some(((stop - start) / step + 1e-9).floor() as usize + 1),
+            _ => None,
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            Analysis::Op => String::from("op"),
+            Analysis::Dc {
+                src,
+                start,
+                stop,
+                step,
+                nested,
+            } => {
+                let s = format!("dc {} {:e} {:e} {:e}", src, start, stop, step);
+                match nested {
+                    Some(nested) => format!("{} {}", s, nested),
+                    None => s,
+                }
+            }
+            Analysis::Ac(sweep) => format!("ac {}", sweep),
+            Analysis::Tran {
+                tstep,
+                tstop,
+                tstart,
+                tmax,
+                uic,
+            } => {
+                let mut s = format!("tran {:e} {:e}", tstep, tstop);
+                // tmax may only be given after tstart
+                if tstart.is_some() || tmax.is_some() {
+                    s.push_str(&format!(" {:e}", tstart.unwrap_or(0.0)));
+                }
+                if let Some(tmax) = tmax {
+                    s.push_str(&format!(" {:e}", tmax));
+                }
+                if *uic {
+                    s.push_str(" uic");
+                }
+                s
+            }
+            Analysis::Noise {
+                output,
+                ref_node,
+                src,
+                sweep,
+                pts_per_summary,
+            } => {
+                let output = match ref_node {
+                    Some(ref_node) => format!("v({},{})", output, ref_node),
+                    None => format!("v({})", output),
+                };
+                let mut s = format!("noise {} {} {}", output, src, sweep);
+                if let Some(pts) = pts_per_summary {
+                    s.push_str(&format!(" {}", pts));
+                }
+                s
+            }
+            Analysis::Tf { output, src } => format!("tf {} {}", output, src),
+            Analysis::Sens { output, sweep } => match sweep {
+                Some(sweep) => format!("sens {} ac {}", output, sweep),
+                None => format!("sens {}", output),
+            },
+            Analysis::Pz {
+                in_pos,
+                in_neg,
+                out_pos,
+                out_neg,
+                transfer,
+                kind,
+            } => {
+                let transfer = match transfer {
+                    PzTransfer::Cur => "cur",
+                    PzTransfer::Vol => "vol",
+                };
+                let kind = match kind {
+                    PzKind::Poles => "pol",
+                    PzKind::Zeros => "zer",
+                    PzKind::Both => "pz",
+                };
+                format!(
+                    "pz {} {} {} {} {} {}",
+                    in_pos, in_neg, out_pos, out_neg, transfer, kind
+                )
+            }
+            Analysis::Disto { sweep, f2overf1 } => match f2overf1 {
+                Some(f2overf1) => format!("disto {} {:e}", sweep, f2overf1),
+                None => format!("disto {}", sweep),
+            },
+        }
+    }
+}
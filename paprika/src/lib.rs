@@ -6,8 +6,19 @@
 //! after loading the netlist.
 //! Dot analysis commands like `.tran 10u 10m` in the netlist is executed after `run` or `bg_run` is sent through `NgSpice_Command`.
 //! Safety must assume that callbacks are called from parallel thread after commanding `bg_run`.
+//!
+//! Callback state is owned by `PkSpice` and its address does not change for the lifetime of `PkSpice`.
+//! Callbacks are delivered as `PkEvent`s, one at a time and in the order ngspice sends them:
+//! from the calling thread during `command`/`circ`, and from the ngspice background thread after `bg_run`.
+//! The receiver from `init_channel` may be moved to and drained from any thread.
+//! With `init_manager`, the manager is locked for the duration of each callback: do not hold its lock while calling into `PkSpice`,
+//! since ngspice calls back synchronously and would deadlock.
 
-use std::{ffi::OsString, sync::Arc};
+use std::{
+    ffi::OsString,
+    marker::PhantomData,
+    sync::{mpsc, Arc, Mutex},
+};
 
 use libc::*;
 #[cfg(unix)]
@@ -17,6 +28,12 @@ use libloading::os::windows::Symbol as RawSymbol;
 use libloading::Library;
 mod structs;
 pub use structs::*;
+mod locate;
+pub use locate::*;
+mod analysis;
+pub use analysis::*;
+mod rawfile;
+pub use rawfile::*;
 mod ngspice;
 use ngspice::*;
 
@@ -24,8 +41,57 @@ use ngspice::*;
 pub enum PkSpiceError {
     /// Sharedspice library is not found
     SharedspiceNotFound(OsString),
+    /// Sharedspice library could not be copied for a new instance
+    CopyFailed(std::io::Error),
+    /// Sharedspice library does not export a required symbol, e.g. because it is not a sharedspice library
+    SymbolNotFound(String),
+    /// Sharedspice library is of an ngspice version older than supported
+    UnsupportedVersion(u32),
+    /// ngspice reported an error for the command
+    CommandFailed(String),
+    /// Background command passed to `command`, which must be sent through `bg_run`, `bg_halt` or `bg_resume`
+    BackgroundCommand(String),
+    /// ngspice could not load the circuit given to `circ`
+    CircFailed,
+    /// ngspice could not set the breakpoint at the time (seconds) given to `set_breakpoint`
+    BreakpointFailed(f64),
+    /// String passed to ngspice contains a NUL byte, so it cannot be passed as a C string
+    NulByte(String),
+}
+
+impl std::fmt::Display for PkSpiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PkSpiceError::SharedspiceNotFound(p) => {
+                write!(f, "sharedspice library not found: {}", p.to_string_lossy())
+            }
+            PkSpiceError::CopyFailed(e) => write!(f, "could not copy sharedspice library: {}", e),
+            PkSpiceError::SymbolNotFound(s) => {
+                write!(f, "symbol {} not found in sharedspice library", s)
+            }
+            PkSpiceError::UnsupportedVersion(v) => write!(
+                f,
+                "ngspice-{} is not supported, ngspice-{} or newer is required",
+                v, MIN_NGSPICE_VERSION
+            ),
+            PkSpiceError::CommandFailed(c) => write!(f, "ngspice could not run {}", c),
+            PkSpiceError::BackgroundCommand(c) => {
+                write!(f, "{} must be sent with bg_run, bg_halt or bg_resume", c)
+            }
+            PkSpiceError::CircFailed => write!(f, "ngspice could not load the circuit"),
+            PkSpiceError::BreakpointFailed(t) => {
+                write!(f, "ngspice could not set a breakpoint at {}s", t)
+            }
+            PkSpiceError::NulByte(s) => write!(f, "{:?} contains a NUL byte", s),
+        }
+    }
 }
 
+impl std::error::Error for PkSpiceError {}
+
+/// Oldest ngspice version supported by paprika
+pub const MIN_NGSPICE_VERSION: u32 = 26;
+
 type NgSpiceInit = extern "C" fn(
     Option<unsafe extern "C" fn(*const c_char, c_int, *const c_void) -> c_int>,
     Option<unsafe extern "C" fn(*const c_char, c_int, *const c_void) -> c_int>,
@@ -37,53 +103,230 @@ type NgSpiceInit = extern "C" fn(
     Option<unsafe extern "C" fn(bool, c_int, *const c_void) -> c_int>,
     *const c_void,
 ) -> c_int;
+type NgSpiceInitSync = extern "C" fn(
+    Option<
+        unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(*mut c_double, c_double, *const c_char, c_int, *const c_void) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(
+            c_double,
+            *mut c_double,
+            c_double,
+            c_int,
+            c_int,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    *const c_int,
+    *const c_void,
+) -> c_int;
 type NgSpiceCommand = extern "C" fn(*const c_char) -> c_int;
 type NgSpiceVecInfo = extern "C" fn(*const c_char) -> *const NgVectorinfo;
+type NgSpiceInitEvt = extern "C" fn(
+    Option<
+        unsafe extern "C" fn(
+            c_int,
+            c_double,
+            c_double,
+            *const c_char,
+            *const c_void,
+            c_int,
+            c_int,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    Option<
+        unsafe extern "C" fn(
+            c_int,
+            c_int,
+            *const c_char,
+            *const c_char,
+            c_int,
+            *const c_void,
+        ) -> c_int,
+    >,
+    *const c_void,
+) -> c_int;
+type NgSpiceEvtNodeInfo = extern "C" fn(*const c_char) -> *const NgEvtSharedData;
+type NgSpiceAllEvtNodes = extern "C" fn() -> *const *const c_char;
+type NgCmInputPath = extern "C" fn(*const c_char) -> *const c_char;
+type NgSpiceCirc = extern "C" fn(*const *const c_char) -> c_int;
 type NgSpiceCurPlot = extern "C" fn() -> *const c_char;
 type NgSpiceAllPlots = extern "C" fn() -> *const *const c_char;
 type NgSpiceAllVecs = extern "C" fn(*const c_char) -> *const *const c_char;
 type NgSpiceRunning = extern "C" fn() -> bool;
+type NgSpiceSetBkpt = extern "C" fn(c_double) -> bool;
 
 #[allow(dead_code)]
 struct VTableV0 {
     init: RawSymbol<NgSpiceInit>,
+    init_sync: Option<RawSymbol<NgSpiceInitSync>>,
 
     command: RawSymbol<NgSpiceCommand>,
     get_vec_info: RawSymbol<NgSpiceVecInfo>,
+    cm_input_path: Option<RawSymbol<NgCmInputPath>>,
+    get_evt_node_info: Option<RawSymbol<NgSpiceEvtNodeInfo>>,
+    get_all_evt_nodes: Option<RawSymbol<NgSpiceAllEvtNodes>>,
+    init_evt: Option<RawSymbol<NgSpiceInitEvt>>,
+    circ: RawSymbol<NgSpiceCirc>,
 
     get_cur_plot: RawSymbol<NgSpiceCurPlot>,
     get_all_plots: RawSymbol<NgSpiceAllPlots>,
     get_all_vecs: RawSymbol<NgSpiceAllVecs>,
     is_running: RawSymbol<NgSpiceRunning>,
+    set_bkpt: Option<RawSymbol<NgSpiceSetBkpt>>,
 }
 
 impl VTableV0 {
-    unsafe fn get_symbol<T>(lib: &Library, sname: &[u8]) -> RawSymbol<T> {
-        let symbol = lib.get(sname).unwrap();
-        libloading::Symbol::<T>::into_raw(symbol)
+    unsafe fn get_symbol<T>(lib: &Library, sname: &[u8]) -> Result<RawSymbol<T>, PkSpiceError> {
+        match lib.get(sname) {
+            Ok(symbol) => Ok(libloading::Symbol::<T>::into_raw(symbol)),
+            Err(_) => Err(PkSpiceError::SymbolNotFound(
+                String::from_utf8_lossy(sname.strip_suffix(b"\0").unwrap_or(sname)).into_owned(),
+            )),
+        }
     }
 
-    unsafe fn new(lib: &Library) -> VTableV0 {
+    unsafe fn new(lib: &Library) -> Result<VTableV0, PkSpiceError> {
         // get symbols (same order as they appear in sharedspice.h)
-        VTableV0 {
-            init: VTableV0::get_symbol::<NgSpiceInit>(lib, b"ngSpice_Init\0"),
-            // b"ngSpice_Init_Sync\0";
-            command: VTableV0::get_symbol::<NgSpiceCommand>(lib, b"ngSpice_Command\0"),
-            get_vec_info: VTableV0::get_symbol::<NgSpiceVecInfo>(lib, b"ngGet_Vec_Info\0"),
-            // b"ngCM_Input_Path\0";
-            // b"ngGet_Evt_NodeInfo\0";
-            // b"ngSpice_AllEvtNodes\0";
-            // b"ngSpice_Init_Evt\0";
-            // b"ngSpice_Circ\0";
-            get_cur_plot: VTableV0::get_symbol::<NgSpiceCurPlot>(lib, b"ngSpice_CurPlot\0"),
-            get_all_plots: VTableV0::get_symbol::<NgSpiceAllPlots>(lib, b"ngSpice_AllPlots\0"),
-            get_all_vecs: VTableV0::get_symbol::<NgSpiceAllVecs>(lib, b"ngSpice_AllVecs\0"),
-            is_running: VTableV0::get_symbol::<NgSpiceRunning>(lib, b"ngSpice_running\0"),
-            // b"ngSpice_SetBkpt\0";
+        // XSPICE symbols are absent if ngspice is built without XSPICE,
+        // ngSpice_Init_Sync and ngSpice_SetBkpt are absent from older versions
+        Ok(VTableV0 {
+            init: VTableV0::get_symbol::<NgSpiceInit>(lib, b"ngSpice_Init\0")?,
+            init_sync: VTableV0::get_symbol::<NgSpiceInitSync>(lib, b"ngSpice_Init_Sync\0").ok(),
+            command: VTableV0::get_symbol::<NgSpiceCommand>(lib, b"ngSpice_Command\0")?,
+            get_vec_info: VTableV0::get_symbol::<NgSpiceVecInfo>(lib, b"ngGet_Vec_Info\0")?,
+            cm_input_path: VTableV0::get_symbol::<NgCmInputPath>(lib, b"ngCM_Input_Path\0").ok(),
+            get_evt_node_info: VTableV0::get_symbol::<NgSpiceEvtNodeInfo>(
+                lib,
+                b"ngGet_Evt_NodeInfo\0",
+            )
+            .ok(),
+            get_all_evt_nodes: VTableV0::get_symbol::<NgSpiceAllEvtNodes>(
+                lib,
+                b"ngSpice_AllEvtNodes\0",
+            )
+            .ok(),
+            init_evt: VTableV0::get_symbol::<NgSpiceInitEvt>(lib, b"ngSpice_Init_Evt\0").ok(),
+            circ: VTableV0::get_symbol::<NgSpiceCirc>(lib, b"ngSpice_Circ\0")?,
+            get_cur_plot: VTableV0::get_symbol::<NgSpiceCurPlot>(lib, b"ngSpice_CurPlot\0")?,
+            get_all_plots: VTableV0::get_symbol::<NgSpiceAllPlots>(lib, b"ngSpice_AllPlots\0")?,
+            get_all_vecs: VTableV0::get_symbol::<NgSpiceAllVecs>(lib, b"ngSpice_AllVecs\0")?,
+            is_running: VTableV0::get_symbol::<NgSpiceRunning>(lib, b"ngSpice_running\0")?,
+            set_bkpt: VTableV0::get_symbol::<NgSpiceSetBkpt>(lib, b"ngSpice_SetBkpt\0").ok(),
+        })
+    }
+
+    /// returns the vtable of lib, or an error if its ngspice version is too old.
+    /// there is a single vtable revision: no sharedspice function has changed signature since MIN_NGSPICE_VERSION,
+    /// and the functions added since are loaded as optional symbols, checked for where they are used, rather than by version
+    unsafe fn for_version(lib: &Library, version: Option<u32>) -> Result<VTableV0, PkSpiceError> {
+        match version {
+            Some(v) if v < MIN_NGSPICE_VERSION => Err(PkSpiceError::UnsupportedVersion(v)),
+            _ => VTableV0::new(lib),
         }
     }
 }
 
+/// Callback from ngspice, as delivered by `PkSpice`
+#[derive(Clone, Debug)]
+pub enum PkEvent {
+    /// Callback known as SendChar in Ngspice User's Manual
+    Char { msg: String, id: i32 },
+    /// Callback known as SendStat in Ngspice User's Manual
+    Stat { msg: String, id: i32 },
+    /// Callback known as ControlledExit in Ngspice User's Manual
+    Exit {
+        status: i32,
+        is_immediate: bool,
+        is_quit: bool,
+        id: i32,
+    },
+    /// Callback known as SendInitData in Ngspice User's Manual
+    Init { vecinfo: PkVecinfoall, id: i32 },
+    /// Callback known as SendData in Ngspice User's Manual
+    Data {
+        vecvalues: PkVecvaluesall,
+        count: i32,
+        id: i32,
+    },
+    /// Callback known as BGThreadRunning in Ngspice User's Manual
+    BgState { is_fin: bool, id: i32 },
+    /// Callback known as SendEvtData in Ngspice User's Manual, registered with `init_evt`
+    /// Value of the event node node_index at time step, mode is the analysis mode (op, dc, tran) ngspice is in
+    EvtData {
+        node_index: i32,
+        step: f64,
+        dvalue: f64,
+        svalue: String,
+        mode: i32,
+        id: i32,
+    },
+    /// Callback known as SendInitEvtData in Ngspice User's Manual, registered with `init_evt`
+    /// Called once per event node, to build up the dictionary of event nodes
+    InitEvtData {
+        node_index: i32,
+        max_index: i32,
+        name: String,
+        udn_name: String,
+        id: i32,
+    },
+}
+
+impl PkEvent {
+    /// Calls the callback of manager corresponding to self
+    pub fn dispatch<T: PkSpiceManager + ?Sized>(self, manager: &mut T) {
+        match self {
+            PkEvent::Char { msg, id } => manager.cb_send_char(msg, id),
+            PkEvent::Stat { msg, id } => manager.cb_send_stat(msg, id),
+            PkEvent::Exit {
+                status,
+                is_immediate,
+                is_quit,
+                id,
+            } => manager.cb_ctrldexit(status, is_immediate, is_quit, id),
+            PkEvent::Init { vecinfo, id } => manager.cb_send_init(vecinfo, id),
+            PkEvent::Data {
+                vecvalues,
+                count,
+                id,
+            } => manager.cb_send_data(vecvalues, count, id),
+            PkEvent::BgState { is_fin, id } => manager.cb_bgt_state(is_fin, id),
+            PkEvent::EvtData { .. } | PkEvent::InitEvtData { .. } => {} // no PkSpiceManager callbacks
+        }
+    }
+}
+
+/// Callbacks registered through ngSpice_Init_Sync, called from the simulation thread during a transient simulation.
+/// External sources are declared in the netlist with the `external` keyword, e.g. `V1 in 0 dc 0 external`.
+pub trait PkSpiceSync: Send {
+    /// Callback known as GetVSRCData in Ngspice User's Manual
+    /// Returns the voltage of the external voltage source named node at time.
+    fn cb_get_vsrc(&mut self, time: f64, node: &str, id: i32) -> f64;
+    /// Callback known as GetISRCData in Ngspice User's Manual
+    /// Returns the current of the external current source named node at time.
+    fn cb_get_isrc(&mut self, time: f64, node: &str, id: i32) -> f64;
+    /// Callback known as GetSyncData in Ngspice User's Manual
+    /// May shorten the next time step by modifying delta. location is the location of the call in dctran.c.
+    #[allow(unused_variables)]
+    fn cb_get_sync(
+        &mut self,
+        time: f64,
+        delta: &mut f64,
+        old_delta: f64,
+        redostep: i32,
+        id: i32,
+        location: i32,
+    ) -> i32 {
+        0
+    }
+}
+
 pub trait PkSpiceManager {
     /// Callback known as SendChar in Ngspice User's Manual
     fn cb_send_char(&mut self, msg: String, id: i32);
@@ -98,25 +341,27 @@ pub trait PkSpiceManager {
     /// Callback known as BGThreadRunning in Ngspice User's Manual
     fn cb_bgt_state(&mut self, is_fin: bool, id: i32);
 }
-/// Represents a link to the sharedspice library
-pub struct PkSpice<T>
-where
-    T: PkSpiceManager,
-{
+/// Represents a link to the sharedspice library.
+/// T is the manager type of the deprecated `init`, and may be left out otherwise.
+pub struct PkSpice<T = ()> {
     #[allow(dead_code)]
     library: Library,
     api: VTableV0,
-    manager: Option<Arc<T>>,
+    /// callback state, boxed so that the address registered with ngspice does not change when self moves
+    sink: Box<PkSink>,
+    /// identification number of this instance, as passed to callbacks
+    id: i32,
+    /// ngspice version, e.g. 39 for ngspice-39
+    version: Option<u32>,
+    manager: PhantomData<fn() -> T>,
 }
 
-impl<T> PkSpice<T>
-where
-    T: PkSpiceManager,
-{
+impl<T> PkSpice<T> {
     /// Links to a sharedspice library given by path.
-    /// Returns error if the file given by path does not exist.
-    /// Crashes if any expected symbols are not found, which will happen if path points to an incorrect file, or to a much older version of sharedspice.
-    pub fn new(path: &std::ffi::OsStr) -> Result<PkSpice<T>, PkSpiceError> {
+    /// Returns error if the file given by path does not exist, if it does not export a required symbol,
+    /// which will happen if path points to an incorrect file, or if it is of an unsupported ngspice version.
+    /// Initializes ngspice without a handler to detect its version.
+    pub fn new(path: &std::ffi::OsStr) -> Result<Self, PkSpiceError> {
         unsafe {
             let lib = match Library::new(path) {
                 Ok(lib) => lib,
@@ -124,60 +369,295 @@ where
                     return Err(PkSpiceError::SharedspiceNotFound(path.to_os_string()));
                 }
             };
-            let vtable = VTableV0::new(&lib); // todo handle symbol not found
+            let sink = Box::<PkSink>::default();
+            let version = Self::detect_version(&lib, &sink)?;
+            let vtable = VTableV0::for_version(&lib, version)?;
             Ok(PkSpice {
                 library: lib,
                 api: vtable,
-                manager: None,
+                sink,
+                id: 0,
+                version,
+                manager: PhantomData,
             })
         }
     }
+    /// Returns the ngspice version from the output of the `version` command, e.g. `39` for ngspice-39
+    unsafe fn detect_version(lib: &Library, sink: &PkSink) -> Result<Option<u32>, PkSpiceError> {
+        let init = VTableV0::get_symbol::<NgSpiceInit>(lib, b"ngSpice_Init\0")?;
+        let command = VTableV0::get_symbol::<NgSpiceCommand>(lib, b"ngSpice_Command\0")?;
+        let (tx, rx) = mpsc::channel();
+        sink.set_handler(Some(Box::new(move |event| {
+            if let PkEvent::Char { msg, .. } = event {
+                let _ = tx.send(msg);
+            }
+        })));
+        init(
+            Some(cbw_send_char),
+            Some(cbw_send_stat),
+            Some(cbw_controlled_exit),
+            Some(cbw_send_data),
+            Some(cbw_send_init_data),
+            Some(cbw_bgthread_running),
+            sink as *const PkSink as *const c_void,
+        );
+        let ccmdstr = std::ffi::CString::new("version -s").unwrap();
+        command(ccmdstr.as_ptr());
+        sink.set_handler(None);
+        Ok(parse_version(&rx.try_iter().collect::<Vec<_>>().join("\n")))
+    }
+    /// Returns the ngspice version of the library, if it could be detected
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+    /// Links to a private copy of the sharedspice library given by path, for running several simulations in parallel.
+    /// Sharedspice keeps its state in globals, so each instance needs its own copy of the library file:
+    /// the copy is placed in dir and named after id, which identifies the instance in callbacks once `init_sync` is called.
+    pub fn new_copy(
+        path: &std::ffi::OsStr,
+        dir: &std::path::Path,
+        id: i32,
+    ) -> Result<Self, PkSpiceError> {
+        let src = std::path::Path::new(path);
+        let mut name = src
+            .file_stem()
+            .ok_or_else(|| PkSpiceError::SharedspiceNotFound(path.to_os_string()))?
+            .to_os_string();
+        name.push(format!("_{}", id));
+        let mut dst = dir.join(name);
+        if let Some(ext) = src.extension() {
+            dst.set_extension(ext);
+        }
+        std::fs::copy(src, &dst).map_err(PkSpiceError::CopyFailed)?;
+        let mut spice = Self::new(dst.as_os_str())?;
+        spice.id = id;
+        Ok(spice)
+    }
+    /// Returns the identification number of this instance
+    pub fn id(&self) -> i32 {
+        self.id
+    }
     /// API function known as ngSpice_Init in Ngspice User's Manual
-    pub fn init(&mut self, manager: Option<Arc<T>>) -> i32 {
-        // drop existing manager
-        // keep reference to new manager
-        match manager {
-            Some(m) => {
-                let ret1 = (self.api.init)(
-                    Some(cbw_send_char::<T>),
-                    Some(cbw_send_stat::<T>),
-                    Some(cbw_controlled_exit::<T>),
-                    Some(cbw_send_data::<T>),
-                    Some(cbw_send_init_data::<T>),
-                    Some(cbw_bgthread_running::<T>),
-                    &*m as *const _ as *const c_void,
-                );
-                self.manager = Some(m); // drop the previous manager, AFTER the new manager is registered
-                ret1
+    /// Registers manager to receive callbacks. The manager is locked for the duration of each callback.
+    pub fn init_manager<M>(&mut self, manager: Arc<Mutex<M>>) -> i32
+    where
+        M: PkSpiceManager + Send + 'static,
+    {
+        self.init_handler(move |event| {
+            if let Ok(mut m) = manager.lock() {
+                event.dispatch(&mut *m);
+            }
+        })
+    }
+    /// API function known as ngSpice_Init in Ngspice User's Manual
+    /// Returns a receiver through which all callbacks are delivered as events.
+    pub fn init_channel(&mut self) -> mpsc::Receiver<PkEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.init_handler(move |event| {
+            // receiver dropped - nobody is listening
+            let _ = tx.send(event);
+        });
+        rx
+    }
+    /// API function known as ngSpice_Init in Ngspice User's Manual
+    /// Registers handler to be called with every callback as an event.
+    pub fn init_handler<F>(&mut self, handler: F) -> i32
+    where
+        F: FnMut(PkEvent) + Send + 'static,
+    {
+        self.sink.set_handler(Some(Box::new(handler)));
+        (self.api.init)(
+            Some(cbw_send_char),
+            Some(cbw_send_stat),
+            Some(cbw_controlled_exit),
+            Some(cbw_send_data),
+            Some(cbw_send_init_data),
+            Some(cbw_bgthread_running),
+            &*self.sink as *const PkSink as *const c_void,
+        )
+    }
+    /// API function known as ngSpice_Init in Ngspice User's Manual
+    /// Initializes ngspice without callbacks, unregistering any previous handler.
+    pub fn init_none(&mut self) -> i32 {
+        let ret1 = (self.api.init)(None, None, None, None, None, None, std::ptr::null());
+        self.sink.set_handler(None); // drop the previous handler, AFTER callbacks are unregistered
+        ret1
+    }
+    /// API function known as ngSpice_Init_Sync in Ngspice User's Manual
+    /// Registers sync to supply external source values and time step synchronization, and sets the instance id
+    /// reported by all callbacks. Must be called after one of the init functions. If sync is None, only the id is set.
+    /// Returns 1 without setting anything if the ngspice version does not support ngSpice_Init_Sync.
+    pub fn init_sync(&mut self, sync: Option<Box<dyn PkSpiceSync>>, id: i32) -> i32 {
+        let init_sync = match &self.api.init_sync {
+            Some(init_sync) => init_sync,
+            None => return 1,
+        };
+        self.id = id;
+        let user = &*self.sink as *const PkSink as *const c_void;
+        match sync {
+            Some(sync) => {
+                self.sink.set_sync(Some(sync));
+                init_sync(
+                    Some(cbw_get_vsrc_data),
+                    Some(cbw_get_isrc_data),
+                    Some(cbw_get_sync_data),
+                    &self.id,
+                    user,
+                )
             }
             None => {
-                let ret1 = (self.api.init)(None, None, None, None, None, None, std::ptr::null());
-                self.manager = None; // drop the previous manager, AFTER the new manager is registered
+                let ret1 = init_sync(None, None, None, &self.id, user);
+                self.sink.set_sync(None);
                 ret1
             }
         }
     }
     /// API function known as ngSpice_Command in Ngspice User's Manual
     /// If cmdstr is an empty string, NULL is sent to ngSpice_Command, which clears the internal control structures.
-    pub fn command(&self, cmdstr: &str) -> bool {
+    /// Background commands (`bg_*`) are rejected, use `bg_run`, `bg_halt` and `bg_resume` instead.
+    /// Returns an error if ngspice reports one for the command, or if cmdstr contains a NUL byte.
+    pub fn command(&self, cmdstr: &str) -> Result<(), PkSpiceError> {
+        if cmdstr.find("bg_") == Some(0) {
+            return Err(PkSpiceError::BackgroundCommand(cmdstr.to_string()));
+        }
+        self.command_unchecked(cmdstr)
+    }
+    /// Runs the loaded circuit in the ngspice background thread and returns immediately.
+    /// Callbacks are called from the background thread until it finishes:
+    /// `cb_bgt_state` reports the thread starting and finishing, `cb_send_stat` reports progress (see `parse_stat`).
+    /// Returns an error if ngspice could not start the thread.
+    pub fn bg_run(&self) -> Result<(), PkSpiceError> {
+        self.command_unchecked("bg_run")
+    }
+    /// Halts the simulation running in the background thread. The simulation may be continued with `bg_resume`.
+    /// Returns an error if ngspice reports one.
+    pub fn bg_halt(&self) -> Result<(), PkSpiceError> {
+        self.command_unchecked("bg_halt")
+    }
+    /// Resumes a simulation halted with `bg_halt` in the background thread.
+    /// Returns an error if ngspice could not resume it.
+    pub fn bg_resume(&self) -> Result<(), PkSpiceError> {
+        self.command_unchecked("bg_resume")
+    }
+    fn command_unchecked(&self, cmdstr: &str) -> Result<(), PkSpiceError> {
         let ret = if cmdstr.is_empty() {
             (self.api.command)(std::ptr::null())
-        }
-        // have users spawn their own threads instead
-        else if cmdstr.find("bg_") == Some(0) {
-            0
         } else {
-            let ccmdstr = std::ffi::CString::new(cmdstr).unwrap();
+            let ccmdstr = c_string(cmdstr)?;
             (self.api.command)(ccmdstr.as_ptr())
         };
-        ret != 0
+        match ret {
+            0 => Ok(()),
+            _ => Err(PkSpiceError::CommandFailed(cmdstr.to_string())),
+        }
+    }
+
+    /// API function known as ngSpice_Circ in Ngspice User's Manual
+    /// Loads the netlist given by lines, one line per element, without going through a file.
+    /// The first line is the title line and the last line should be `.end`. Returns an error if the netlist was not loaded.
+    pub fn circ(&self, lines: &[String]) -> Result<(), PkSpiceError> {
+        let clines = lines
+            .iter()
+            .map(|l| c_string(l))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut ptrs: Vec<*const c_char> = clines.iter().map(|l| l.as_ptr()).collect();
+        ptrs.push(std::ptr::null()); // array is terminated by a null pointer
+        match (self.api.circ)(ptrs.as_ptr()) {
+            0 => Ok(()),
+            _ => Err(PkSpiceError::CircFailed),
+        }
     }
 
-    pub fn get_vec_info(&self, vecname: &str) -> PkVectorinfo {
+    /// API function known as ngGet_Vec_Info in Ngspice User's Manual
+    /// Returns the vector vecname, e.g. `tran1.out`, or None if it is not found.
+    pub fn get_vec_info(&self, vecname: &str) -> Option<PkVectorinfo> {
+        let cvecname = std::ffi::CString::new(vecname).ok()?;
         unsafe {
-            let cvecname = std::ffi::CString::new(vecname).unwrap();
             let pvectorinfo = (self.api.get_vec_info)(cvecname.as_ptr());
-            (*pvectorinfo).to_pk()
+            if pvectorinfo.is_null() {
+                None
+            } else {
+                Some((*pvectorinfo).to_pk())
+            }
+        }
+    }
+
+    /// API function known as ngCM_Input_Path in Ngspice User's Manual
+    /// Sets the input path for files loaded by code models if path is some.
+    /// Returns the current input path, if any. Returns None if ngspice is built without XSPICE, an error if path contains a NUL byte.
+    pub fn cm_input_path(&self, path: Option<&str>) -> Result<Option<String>, PkSpiceError> {
+        let cm_input_path = match &self.api.cm_input_path {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        let cpath = path.map(c_string).transpose()?;
+        unsafe {
+            let pcstr = cm_input_path(cpath.as_ref().map_or(std::ptr::null(), |p| p.as_ptr()));
+            if pcstr.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(
+                    std::ffi::CStr::from_ptr(pcstr)
+                        .to_string_lossy()
+                        .into_owned(),
+                ))
+            }
+        }
+    }
+    /// Loads the XSPICE code model library (`.cm` file) at path, through the `codemodel` command.
+    /// Returns the result of `command`.
+    pub fn load_codemodel(&self, path: &str) -> Result<(), PkSpiceError> {
+        self.command(&format!("codemodel {}", path))
+    }
+    /// API function known as ngSpice_Init_Evt in Ngspice User's Manual
+    /// Registers callbacks for event (XSPICE digital) nodes, delivered to the handler registered with one of the init functions
+    /// as `PkEvent::InitEvtData` and `PkEvent::EvtData`. Must be called after one of the init functions.
+    /// Returns 1 if ngspice is built without XSPICE.
+    pub fn init_evt(&mut self) -> i32 {
+        match &self.api.init_evt {
+            Some(init_evt) => init_evt(
+                Some(cbw_send_evt_data),
+                Some(cbw_send_init_evt_data),
+                &*self.sink as *const PkSink as *const c_void,
+            ),
+            None => 1,
+        }
+    }
+    /// API function known as ngGet_Evt_NodeInfo in Ngspice User's Manual
+    /// Returns all data points of the event node nodename (`nodename` or `plotname.nodename`), or None if there is no such node.
+    /// Returns an error if nodename contains a NUL byte.
+    pub fn get_evt_node_info(
+        &self,
+        nodename: &str,
+    ) -> Result<Option<PkEvtSharedData>, PkSpiceError> {
+        let get_evt_node_info = match &self.api.get_evt_node_info {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+        let cnodename = c_string(nodename)?;
+        unsafe {
+            let pevtshareddata = get_evt_node_info(cnodename.as_ptr());
+            if pevtshareddata.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some((*pevtshareddata).to_pk()))
+            }
+        }
+    }
+    /// API function known as ngSpice_AllEvtNodes in Ngspice User's Manual
+    /// Returns the names of all event nodes
+    pub fn get_all_evt_nodes(&self) -> Vec<String> {
+        let get_all_evt_nodes = match &self.api.get_all_evt_nodes {
+            Some(f) => f,
+            None => return vec![],
+        };
+        unsafe {
+            let ppcstr = get_all_evt_nodes();
+            if ppcstr.is_null() {
+                vec![]
+            } else {
+                c_strings(ppcstr)
+            }
         }
     }
 
@@ -198,17 +678,154 @@ where
         }
     }
 
-    pub fn get_all_vecs(&self, plotname: &str) -> Vec<String> {
+    /// Returns the names of the vectors of the plot named plotname, or an error if plotname contains a NUL byte.
+    pub fn get_all_vecs(&self, plotname: &str) -> Result<Vec<String>, PkSpiceError> {
+        let cplotname = c_string(plotname)?;
         unsafe {
-            let cplotname = std::ffi::CString::new(plotname).unwrap();
             let ppcstr = (self.api.get_all_vecs)(cplotname.as_ptr());
-            c_strings(ppcstr)
+            Ok(c_strings(ppcstr))
         }
     }
 
+    /// Returns every vector of the plot named plotname (e.g. `tran1`, or the current plot from `get_cur_plot`),
+    /// with its unit and data. Vectors which cannot be retrieved are omitted.
+    /// The scale is the one ngspice reported through SendInitData when the plot was simulated, so plots are only
+    /// given a scale if callbacks were registered with one of the init functions (other than `init_none`).
+    pub fn get_plot(&self, plotname: &str) -> SimPlot {
+        let vecs = self
+            .get_all_vecs(plotname)
+            .unwrap_or_default() // no plot is named with a NUL byte
+            .into_iter()
+            .filter_map(|name| {
+                let info = self.get_vec_info(&format!("{}.{}", plotname, name))?;
+                let data = match (info.realdata, info.compdata) {
+                    (Some(real), _) => SimData::Real(real),
+                    (None, Some(comp)) => SimData::Complex(comp),
+                    (None, None) => return None,
+                };
+                Some(SimVector {
+                    name,
+                    unit: SimUnit::from_stype(info.stype),
+                    data,
+                    scale: None,
+                })
+            })
+            .collect();
+        SimPlot::new(
+            plotname.to_string(),
+            vecs,
+            self.sink.scale(plotname).as_deref(),
+        )
+    }
+
     pub fn is_running(&self) -> bool {
         (self.api.is_running)()
     }
+
+    /// API function known as ngSpice_SetBkpt in Ngspice User's Manual
+    /// Stops the transient simulation of the loaded circuit at time (seconds), after which it may be continued with `bg_resume`.
+    /// Must be called after the circuit is loaded. Returns an error if the breakpoint was not set,
+    /// `PkSpiceError::SymbolNotFound` if the ngspice version does not support breakpoints.
+    pub fn set_breakpoint(&self, time: f64) -> Result<(), PkSpiceError> {
+        let set_bkpt = self
+            .api
+            .set_bkpt
+            .as_ref()
+            .ok_or_else(|| PkSpiceError::SymbolNotFound(String::from("ngSpice_SetBkpt")))?;
+        if set_bkpt(time) {
+            Ok(())
+        } else {
+            Err(PkSpiceError::BreakpointFailed(time))
+        }
+    }
+}
+
+impl<T> PkSpice<T>
+where
+    T: PkSpiceManager + Send + 'static,
+{
+    /// API function known as ngSpice_Init in Ngspice User's Manual
+    /// Registers manager to receive callbacks, or unregisters any previous manager or handler if None.
+    /// Callbacks mutate the manager, so it is taken out of the `Arc`, which must not be shared:
+    /// returns 1 without registering anything if other references to manager exist.
+    #[deprecated(
+        note = "use init_manager, init_handler or init_channel, and init_none to unregister"
+    )]
+    pub fn init(&mut self, manager: Option<Arc<T>>) -> i32 {
+        match manager.map(Arc::try_unwrap) {
+            Some(Ok(manager)) => self.init_manager(Arc::new(Mutex::new(manager))),
+            Some(Err(_)) => 1,
+            None => self.init_none(),
+        }
+    }
+}
+
+impl<T> Drop for PkSpice<T> {
+    fn drop(&mut self) {
+        // the background thread calls back until it has finished, and is still returning from the last callback
+        // when its receiver may already drop self: halt it, and wait for that callback before the sink is freed
+        if self.is_running() {
+            let _ = self.bg_halt(); // nothing left to do if it fails
+        }
+        self.sink.wait_idle();
+    }
+}
+
+/// Parses a status message as sent to `cb_send_stat` during a simulation, e.g. `tran: 34.5%`.
+/// Returns the status name and the progress in percent, or None if msg does not report progress, e.g. `--ready--`.
+pub fn parse_stat(msg: &str) -> Option<(&str, f32)> {
+    let (name, pct) = msg.rsplit_once(':')?;
+    let pct = pct.trim().strip_suffix('%')?.trim().parse().ok()?;
+    Some((name.trim(), pct))
+}
+
+/// Parses the ngspice version from the output of the `version` command, e.g. 39 from `** ngspice-39 : Circuit level simulation program`
+pub fn parse_version(output: &str) -> Option<u32> {
+    let (_, rest) = output.split_once("ngspice-")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Parses a number in spice notation, e.g. `10u`, `1.5meg`, `2e-3`, `5ms`.
+/// Scale factors are case insensitive, and any trailing unit (e.g. `s` in `5ms`) is ignored.
+pub fn parse_spice_number(s: &str) -> Option<f64> {
+    let s = s.trim().to_ascii_lowercase();
+    // longest prefix which is a number
+    let split = (1..=s.len())
+        .rev()
+        .find(|&i| s.is_char_boundary(i) && s[..i].parse::<f64>().is_ok())?;
+    let (num, suffix) = s.split_at(split);
+    if suffix.starts_with("mil") {
+        return num.parse::<f64>().ok().map(|x| x * 25.4e-6);
+    }
+    // scale by exponent, so that e.g. 10u is parsed exactly as 10e-6
+    let exp = if suffix.starts_with("meg") {
+        6
+    } else {
+        match suffix.chars().next() {
+            None => 0,
+            Some('t') => 12,
+            Some('g') => 9,
+            Some('k') => 3,
+            Some('m') => -3,
+            Some('u') => -6,
+            Some('n') => -9,
+            Some('p') => -12,
+            Some('f') => -15,
+            Some(c) if c.is_alphabetic() => 0, // unit only
+            _ => return None,
+        }
+    };
+    let x: f64 = num.parse().ok()?;
+    format!("{:e}", x).split_once('e').and_then(|(m, e)| {
+        format!("{}e{}", m, e.parse::<i32>().ok()? + exp)
+            .parse()
+            .ok()
+    })
+}
+
+fn c_string(s: &str) -> Result<std::ffi::CString, PkSpiceError> {
+    std::ffi::CString::new(s).map_err(|_| PkSpiceError::NulByte(s.to_string()))
 }
 
 unsafe fn c_strings(ptr: *const *const c_char) -> Vec<String> {
@@ -0,0 +1,70 @@
+//! Sharedspice library discovery
+
+use std::path::{Path, PathBuf};
+
+/// Environment variable which may hold the path of the sharedspice library, or of the directory containing it
+pub const SHAREDSPICE_ENV: &str = "SHAREDSPICE_PATH";
+
+/// File names of the sharedspice library on this platform, in order of preference
+pub fn sharedspice_names() -> &'static [&'static str] {
+    #[cfg(target_family = "windows")]
+    {
+        &["ngspice.dll", "ngspice-0.dll", "libngspice-0.dll"]
+    }
+    #[cfg(target_os = "macos")]
+    {
+        &["libngspice.dylib", "libngspice.0.dylib"]
+    }
+    #[cfg(not(any(target_family = "windows", target_os = "macos")))]
+    {
+        &["libngspice.so", "libngspice.so.0"]
+    }
+}
+
+/// Standard library directories of this platform
+fn lib_dirs() -> Vec<PathBuf> {
+    #[cfg(target_family = "windows")]
+    let dirs: &[&str] = &["C:\\Spice64\\bin-dll", "C:\\Spice64\\bin"];
+    #[cfg(target_os = "macos")]
+    let dirs: &[&str] = &["/opt/homebrew/lib", "/usr/local/lib", "/usr/lib"];
+    #[cfg(not(any(target_family = "windows", target_os = "macos")))]
+    let dirs: &[&str] = &[
+        "/usr/local/lib",
+        "/usr/lib",
+        "/usr/lib/x86_64-linux-gnu",
+        "/usr/lib/aarch64-linux-gnu",
+        "/usr/lib64",
+        "/lib",
+    ];
+    dirs.iter().map(PathBuf::from).collect()
+}
+
+/// Returns the paths to try for the sharedspice library, in order:
+/// the `SHAREDSPICE_PATH` environment variable, config (e.g. from an application's configuration file),
+/// the standard library directories, then the directory of the executable.
+/// Environment variable and config may each be a file or a directory.
+pub fn sharedspice_candidates(config: Option<&Path>) -> Vec<PathBuf> {
+    let env = std::env::var_os(SHAREDSPICE_ENV).map(PathBuf::from);
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf));
+    let mut candidates = vec![];
+    for p in env.iter().map(|p| p.as_path()).chain(config) {
+        if p.is_dir() {
+            candidates.extend(sharedspice_names().iter().map(|name| p.join(name)));
+        } else {
+            candidates.push(p.to_path_buf());
+        }
+    }
+    for d in lib_dirs().into_iter().chain(exe_dir) {
+        candidates.extend(sharedspice_names().iter().map(|name| d.join(name)));
+    }
+    candidates
+}
+
+/// Returns the first existing sharedspice library path from `sharedspice_candidates`, if any
+pub fn locate_sharedspice(config: Option<&Path>) -> Option<PathBuf> {
+    sharedspice_candidates(config)
+        .into_iter()
+        .find(|p| p.is_file())
+}
@@ -1,38 +1,35 @@
+//! paprika command line program
+//! passes commands between the user and ngspice, see `USAGE`
+
 use std::{
-    collections::VecDeque,
-    sync::{Arc, RwLock},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
-// use ::paprika;
 use colored::Colorize;
 use paprika::*;
-#[allow(dead_code)]
+use rustyline::error::ReadlineError;
+
+const USAGE: &str = "usage: paprika [-l <sharedspice library>] [-s <script>] [netlist]
+
+  -l, --lib <path>       sharedspice library, located with SHAREDSPICE_PATH and the standard directories if not given
+  -s, --script <path>    run the commands in the file (one per line) before the prompt
+  -h, --help             print this message
+
+At the prompt, ngspice commands are sent to ngspice. In addition:
+  :dump <vector|plot> [file]  write a vector (e.g. v(out) or tran1.time) or a whole plot (e.g. tran1) as csv,
+                              or as json if file ends in .json. Prints csv if no file is given
+  :script <path>              run the commands in the file
+  :history                    print the line history
+  :quit                       exit (as does ngspice's quit)";
+
+#[derive(Default)]
 struct Manager {
-    sharedres: Arc<RwLock<VecDeque<String>>>,
     quit_flag: bool,
-    vec_char: Vec<String>,
-    vec_stat: Vec<String>,
-    vec_pkvecinfoall: Vec<PkVecinfoall>,
-    vec_pkvecvalsall: Vec<PkVecvaluesall>,
-}
-impl Manager {
-    fn new(arvs: Arc<RwLock<VecDeque<String>>>) -> Manager {
-        Manager {
-            sharedres: arvs,
-            quit_flag: false,
-            vec_char: Vec::<String>::new(),
-            vec_stat: Vec::<String>::new(),
-            vec_pkvecinfoall: Vec::<PkVecinfoall>::new(),
-            vec_pkvecvalsall: Vec::<PkVecvaluesall>::new(),
-        }
-    }
 }
 #[allow(unused_variables)]
 impl paprika::PkSpiceManager for Manager {
     fn cb_send_char(&mut self, msg: String, id: i32) {
-        let mut arvs = self.sharedres.write().unwrap();
-        (*arvs).push_back(msg.clone());
-
         let opt = msg.split_once(' ');
         let (token, msgs) = match opt {
             Some(tup) => (tup.0, tup.1),
@@ -55,56 +52,266 @@ impl paprika::PkSpiceManager for Manager {
         );
         self.quit_flag = true;
     }
-    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {
-        self.vec_pkvecinfoall.push(pkvecinfoall);
-    }
-    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {
-        self.vec_pkvecvalsall.push(pkvecvaluesall);
-    }
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {}
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {}
     fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {
         println!("bgt_state {}; {};", is_fin, id);
     }
 }
 
+/// command line arguments
+#[derive(Default)]
+struct Args {
+    lib: Option<PathBuf>,
+    script: Option<PathBuf>,
+    netlist: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut it = std::env::args().skip(1);
+        while let Some(arg) = it.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Err(String::new()),
+                "-l" | "--lib" => {
+                    args.lib = Some(it.next().ok_or("missing library path")?.into());
+                }
+                "-s" | "--script" => {
+                    args.script = Some(it.next().ok_or("missing script path")?.into());
+                }
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ if args.netlist.is_none() => args.netlist = Some(arg.into()),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+        Ok(args)
+    }
+}
+
+/// returns the plot named name, the vector named name (`plot.vector`, or a vector of the current plot) as a plot of its own,
+/// or None if there is neither
+fn find(spice: &PkSpice, name: &str) -> Option<SimPlot> {
+    if spice.get_all_plots().iter().any(|p| p == name) {
+        return Some(spice.get_plot(name));
+    }
+    let (plot, vec) = match name.split_once('.') {
+        Some((plot, vec)) if spice.get_all_plots().iter().any(|p| p == plot) => {
+            (plot.to_string(), vec)
+        }
+        _ => (spice.get_cur_plot(), name),
+    };
+    let mut plot = spice.get_plot(&plot);
+    plot.vecs.retain(|v| v.name == vec);
+    if plot.vecs.is_empty() {
+        None
+    } else {
+        Some(plot)
+    }
+}
+
+/// returns the vectors of plot as csv, one column per real vector and two (real, imaginary) per complex vector
+fn to_csv(plot: &SimPlot) -> String {
+    let mut header = vec![];
+    for v in &plot.vecs {
+        match v.data {
+            SimData::Real(_) => header.push(v.name.clone()),
+            SimData::Complex(_) => {
+                header.push(format!("re({})", v.name));
+                header.push(format!("im({})", v.name));
+            }
+        }
+    }
+    let mut csv = header.join(",");
+    csv.push('\n');
+    let rows = plot.vecs.iter().map(|v| v.data.len()).max().unwrap_or(0);
+    for i in 0..rows {
+        let mut row = vec![];
+        for v in &plot.vecs {
+            match &v.data {
+                SimData::Real(d) => row.push(d.get(i).map_or(String::new(), |x| x.to_string())),
+                SimData::Complex(d) => {
+                    row.push(d.get(i).map_or(String::new(), |c| c.re.to_string()));
+                    row.push(d.get(i).map_or(String::new(), |c| c.im.to_string()));
+                }
+            }
+        }
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn to_json(plot: &SimPlot) -> Result<String, String> {
+    serde_json::to_string_pretty(plot).map_err(|e| e.to_string())
+}
+
+/// writes the vector or plot name to path, as csv or json depending on its extension, or prints it as csv if path is None
+fn dump(spice: &PkSpice, name: &str, path: Option<&Path>) -> Result<(), String> {
+    let plot = find(spice, name).ok_or_else(|| format!("no vector or plot named {}", name))?;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            print!("{}", to_csv(&plot));
+            return Ok(());
+        }
+    };
+    let s = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => to_json(&plot)?,
+        _ => to_csv(&plot),
+    };
+    std::fs::write(path, s).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// runs every line of the file at path, skipping blank lines and comments (`*`, `#`). Returns false on quit
+fn run_script(
+    spice: &PkSpice,
+    manager: &Arc<Mutex<Manager>>,
+    path: &Path,
+    history: &[String],
+) -> Result<bool, String> {
+    let s = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    for line in s.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('*') || line.starts_with('#') {
+            continue;
+        }
+        if !run_line(spice, manager, line, history) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// runs a line from the prompt or a script. Returns false on quit
+fn run_line(
+    spice: &PkSpice,
+    manager: &Arc<Mutex<Manager>>,
+    line: &str,
+    history: &[String],
+) -> bool {
+    let mut words = line.split_whitespace();
+    let result = match words.next() {
+        None => Ok(()),
+        Some(":quit") => return false,
+        Some(":history") => {
+            for (i, l) in history.iter().enumerate() {
+                println!("{:5}  {}", i + 1, l);
+            }
+            Ok(())
+        }
+        Some(":script") => match words.next() {
+            Some(path) => match run_script(spice, manager, Path::new(path), history) {
+                Ok(true) => Ok(()),
+                Ok(false) => return false,
+                Err(e) => Err(e),
+            },
+            None => Err(String::from("usage: :script <path>")),
+        },
+        Some(":dump") => match words.next() {
+            Some(name) => dump(spice, name, words.next().map(Path::new)),
+            None => Err(String::from("usage: :dump <vector|plot> [file]")),
+        },
+        Some(cmd) if cmd.starts_with(':') => Err(format!("unknown command {}", cmd)),
+        // background commands are not passed through command
+        Some("bg_run") => spice.bg_run().map_err(|e| e.to_string()),
+        Some("bg_halt") => spice.bg_halt().map_err(|e| e.to_string()),
+        Some("bg_resume") => spice.bg_resume().map_err(|e| e.to_string()),
+        Some(_) => spice.command(line).map_err(|e| e.to_string()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e.red());
+    }
+    !manager.lock().unwrap().quit_flag
+}
+
+/// line history file, in the home directory
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|h| PathBuf::from(h).join(".paprika_history"))
+}
+
 fn main() {
-    let mut spice = PkSpice::<Manager>::new(std::ffi::OsStr::new("ngspice.dll")).unwrap();
-    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
-    let manager = Arc::new(Manager::new(buf));
-
-    spice.init(Some(manager)); // register
-    spice.command("source tran.cir"); // results pointer array starts at same address
-    spice.command("tran 10u 10m"); // ngspice recommends sending in control statements separately, not as part of netlist
-
-    spice.init(None); // unregister
-    spice.command("echo echo command");
-
-    // spice.command("source ac.cir");  // results pointer array starts at same address
-    // spice.command("ac dec 10 1 100k");  // ngspice recommends sending in control statements separately, not as part of netlist
-
-    // // dbg!(manager.lib.running());
-    // let a = manager.lib.get_vec_info("tran1.time");
-    // let a1 = a.realdata.unwrap();
-    // let b = manager.lib.get_vec_info("ac1.frequency");
-    // let b1 = b.compdata.unwrap();
-
-    // dbg!(manager.lib.get_allplots());
-    // dbg!(manager.lib.get_curplotname());
-    // dbg!(manager.lib.get_allvecs("ac2"));
-    // dbg!(manager.lib.get_vec_info("frequency"));
-    // let a = String::from("echo hello");
-    // manager.lib.command(&a.as_str());
-
-    let mut line = String::new();
-    loop {
-        line.clear();
-        let _ = std::io::stdin().read_line(&mut line).unwrap();
-        match line.as_str().split_once("\r\n") {
-            Some(tup) => {
-                spice.command(tup.0);
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("{}", e);
             }
-            None => {
-                spice.command(line.as_str());
-            } // this should only happen for blank inputs {println!("{:?}", line);},
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
         }
+    };
+    let lib = match args.lib.or_else(|| locate_sharedspice(None)) {
+        Some(lib) => lib,
+        None => {
+            eprintln!(
+                "sharedspice library not found - give its path with -l or set {}",
+                SHAREDSPICE_ENV
+            );
+            std::process::exit(1);
+        }
+    };
+    let mut spice: PkSpice = match PkSpice::new(lib.as_os_str()) {
+        Ok(spice) => spice,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let manager = Arc::new(Mutex::new(Manager::default()));
+    spice.init_manager(manager.clone()); // register
+
+    let mut editor = match rustyline::DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = history_path() {
+        let _ = editor.load_history(&path);
+    }
+    let history = |editor: &rustyline::DefaultEditor| -> Vec<String> {
+        editor.history().iter().cloned().collect()
+    };
+
+    let mut running = true;
+    if let Some(netlist) = &args.netlist {
+        running = run_line(
+            &spice,
+            &manager,
+            &format!("source {}", netlist.display()),
+            &[],
+        );
+    }
+    if let (true, Some(script)) = (running, &args.script) {
+        running = match run_script(&spice, &manager, script, &history(&editor)) {
+            Ok(running) => running,
+            Err(e) => {
+                eprintln!("{}", e.red());
+                true
+            }
+        };
+    }
+    while running {
+        match editor.readline("paprika> ") {
+            Ok(line) => {
+                let line = line.trim();
+                if !line.is_empty() {
+                    let _ = editor.add_history_entry(line);
+                }
+                running = run_line(&spice, &manager, line, &history(&editor));
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("{}", e);
+                break;
+            }
+        }
+    }
+    if let Some(path) = history_path() {
+        let _ = editor.save_history(&path);
     }
 }
@@ -1,105 +1,249 @@
 use crate::ngspice::*;
-use crate::PkSpiceManager;
+use crate::{PkEvent, PkSpiceSync};
 use libc::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
-pub unsafe extern "C" fn cbw_send_char<T>(
+/// Handler receiving callbacks as events
+pub type PkHandler = Box<dyn FnMut(PkEvent) + Send>;
+
+/// Callback state owned by `PkSpice`. A pointer to it is registered with ngspice as the user data of every callback,
+/// and stays valid for the lifetime of `PkSpice`.
+/// Events are delivered to the handler one at a time, under the mutex.
+#[derive(Default)]
+pub struct PkSink {
+    handler: Mutex<Option<PkHandler>>,
+    sync: Mutex<Option<Box<dyn PkSpiceSync>>>,
+    /// scale vector of each plot, by plot name, as reported by SendInitData
+    scales: Mutex<HashMap<String, String>>,
+}
+
+impl PkSink {
+    /// returns the name of the scale vector of plot, if ngspice reported one since the callbacks were registered
+    pub fn scale(&self, plot: &str) -> Option<String> {
+        self.scales.lock().ok()?.get(plot).cloned()
+    }
+    pub fn set_handler(&self, handler: Option<PkHandler>) {
+        if let Ok(mut h) = self.handler.lock() {
+            *h = handler;
+        }
+    }
+    pub fn set_sync(&self, sync: Option<Box<dyn PkSpiceSync>>) {
+        if let Ok(mut s) = self.sync.lock() {
+            *s = sync;
+        }
+    }
+    /// waits for the callbacks in progress to return
+    pub fn wait_idle(&self) {
+        drop(self.handler.lock());
+        drop(self.sync.lock());
+    }
+    unsafe fn get<'a>(user: *const c_void) -> &'a PkSink {
+        // safety requires
+        // user points to the PkSink registered with ngspice, which is kept alive by PkSpice
+        &*(user as *const PkSink)
+    }
+    unsafe fn send(user: *const c_void, event: PkEvent) {
+        // a handler which panicked poisons the mutex - drop further events rather than panicking across ffi
+        if let Ok(mut handler) = PkSink::get(user).handler.lock() {
+            if let Some(handler) = handler.as_mut() {
+                handler(event);
+            }
+        }
+    }
+    /// calls f with the registered sync handler, returns 1 (error) if there is none
+    unsafe fn with_sync<F>(user: *const c_void, f: F) -> c_int
+    where
+        F: FnOnce(&mut dyn PkSpiceSync) -> c_int,
+    {
+        match PkSink::get(user).sync.lock() {
+            Ok(mut sync) => match sync.as_mut() {
+                Some(sync) => f(sync.as_mut()),
+                None => 1,
+            },
+            Err(_) => 1,
+        }
+    }
+}
+
+pub unsafe extern "C" fn cbw_send_char(
     msg: *const c_char,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
-    unsafe {
-        <T as PkSpiceManager>::cb_send_char(
-            &mut *(user as *mut T),
-            std::ffi::CStr::from_ptr(msg).to_str().unwrap().to_owned(),
-            id,
-        );
-    }
+) -> c_int {
+    let msg = std::ffi::CStr::from_ptr(msg).to_string_lossy().into_owned();
+    PkSink::send(user, PkEvent::Char { msg, id });
     0
 }
-pub unsafe extern "C" fn cbw_send_stat<T>(
+pub unsafe extern "C" fn cbw_send_stat(
     msg: *const c_char,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
-    unsafe {
-        <T as PkSpiceManager>::cb_send_stat(
-            &mut *(user as *mut T),
-            std::ffi::CStr::from_ptr(msg).to_str().unwrap().to_owned(),
-            id,
-        );
-    }
+) -> c_int {
+    let msg = std::ffi::CStr::from_ptr(msg).to_string_lossy().into_owned();
+    PkSink::send(user, PkEvent::Stat { msg, id });
     0
 }
-pub unsafe extern "C" fn cbw_controlled_exit<T>(
+pub unsafe extern "C" fn cbw_controlled_exit(
     status: c_int,
     immediate: bool,
     exit_on_quit: bool,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
-    unsafe {
-        <T as PkSpiceManager>::cb_ctrldexit(
-            &mut *(user as *mut T),
+) -> c_int {
+    PkSink::send(
+        user,
+        PkEvent::Exit {
             status,
-            immediate,
-            exit_on_quit,
+            is_immediate: immediate,
+            is_quit: exit_on_quit,
             id,
-        );
-    }
+        },
+    );
     0
 }
-pub unsafe extern "C" fn cbw_send_data<T>(
+pub unsafe extern "C" fn cbw_send_data(
     pvecvaluesall: *const NgVecvaluesall,
     count: c_int,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
+) -> c_int {
     // todo: should be an option to bypass this code if the result is not used
     // create native PkVecvaluesall
-    let pkvecinfoall = (*pvecvaluesall).to_pk();
-
-    // call native callback
-    <T as PkSpiceManager>::cb_send_data(&mut *(user as *mut T), pkvecinfoall, count, id);
+    let vecvalues = (*pvecvaluesall).to_pk();
+    PkSink::send(
+        user,
+        PkEvent::Data {
+            vecvalues,
+            count,
+            id,
+        },
+    );
     0
 }
-pub unsafe extern "C" fn cbw_send_init_data<T>(
+pub unsafe extern "C" fn cbw_send_init_data(
     pvecinfoall: *const NgVecinfoall,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
+) -> c_int {
     // todo: should be an option to bypass this code if the result is not used
     // create native PkVecInfoall
-    let pkvecinfoall = (*pvecinfoall).to_pk();
-
-    // call native callback
-    <T as PkSpiceManager>::cb_send_init(&mut *(user as *mut T), pkvecinfoall, id);
+    let vecinfo = (*pvecinfoall).to_pk();
+    // pdvecscale points to the scale vector of the plot, which is also listed
+    let scale = vecinfo
+        .vecs
+        .iter()
+        .find(|v| v.pdvecscale != 0)
+        .and_then(|s| vecinfo.vecs.iter().find(|v| v.pdvec == s.pdvecscale));
+    if let (Some(scale), Ok(mut scales)) = (scale, PkSink::get(user).scales.lock()) {
+        scales.insert(vecinfo.name.clone(), scale.name.clone());
+    }
+    PkSink::send(user, PkEvent::Init { vecinfo, id });
     0
 }
-pub unsafe extern "C" fn cbw_bgthread_running<T>(
+pub unsafe extern "C" fn cbw_bgthread_running(
     finished: bool,
     id: c_int,
     user: *const c_void,
-) -> c_int
-where
-    T: PkSpiceManager,
-{
-    unsafe {
-        <T as PkSpiceManager>::cb_bgt_state(&mut *(user as *mut T), finished, id);
-    }
+) -> c_int {
+    PkSink::send(
+        user,
+        PkEvent::BgState {
+            is_fin: finished,
+            id,
+        },
+    );
+    0
+}
+pub unsafe extern "C" fn cbw_get_vsrc_data(
+    voltage: *mut c_double,
+    time: c_double,
+    node: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int {
+    let node = std::ffi::CStr::from_ptr(node).to_string_lossy();
+    PkSink::with_sync(user, |sync| {
+        *voltage = sync.cb_get_vsrc(time, &node, id);
+        0
+    })
+}
+pub unsafe extern "C" fn cbw_get_isrc_data(
+    current: *mut c_double,
+    time: c_double,
+    node: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int {
+    let node = std::ffi::CStr::from_ptr(node).to_string_lossy();
+    PkSink::with_sync(user, |sync| {
+        *current = sync.cb_get_isrc(time, &node, id);
+        0
+    })
+}
+pub unsafe extern "C" fn cbw_get_sync_data(
+    time: c_double,
+    delta: *mut c_double,
+    old_delta: c_double,
+    redostep: c_int,
+    id: c_int,
+    location: c_int,
+    user: *const c_void,
+) -> c_int {
+    PkSink::with_sync(user, |sync| {
+        sync.cb_get_sync(time, &mut *delta, old_delta, redostep, id, location)
+    })
+}
+pub unsafe extern "C" fn cbw_send_evt_data(
+    node_index: c_int,
+    step: c_double,
+    dvalue: c_double,
+    svalue: *const c_char,
+    _pvalue: *const c_void,
+    _plen: c_int,
+    mode: c_int,
+    id: c_int,
+    user: *const c_void,
+) -> c_int {
+    let svalue = std::ffi::CStr::from_ptr(svalue)
+        .to_string_lossy()
+        .into_owned();
+    PkSink::send(
+        user,
+        PkEvent::EvtData {
+            node_index,
+            step,
+            dvalue,
+            svalue,
+            mode,
+            id,
+        },
+    );
+    0
+}
+pub unsafe extern "C" fn cbw_send_init_evt_data(
+    node_index: c_int,
+    max_index: c_int,
+    name: *const c_char,
+    udn_name: *const c_char,
+    id: c_int,
+    user: *const c_void,
+) -> c_int {
+    let name = std::ffi::CStr::from_ptr(name)
+        .to_string_lossy()
+        .into_owned();
+    let udn_name = std::ffi::CStr::from_ptr(udn_name)
+        .to_string_lossy()
+        .into_owned();
+    PkSink::send(
+        user,
+        PkEvent::InitEvtData {
+            node_index,
+            max_index,
+            name,
+            udn_name,
+            id,
+        },
+    );
     0
 }
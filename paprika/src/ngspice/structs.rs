@@ -5,16 +5,43 @@ use std::ffi::CStr;
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgEvtData {
-    dcop: c_double,
-    step: c_int,
+    dcop: c_int,
+    step: c_double,
     node_value: *const c_char,
 }
+impl NgEvtData {
+    pub unsafe fn to_pk(self) -> PkEvtData {
+        PkEvtData {
+            dcop: self.dcop,
+            step: self.step,
+            node_value: CStr::from_ptr(self.node_value)
+                .to_str()
+                .unwrap()
+                .to_string(),
+        }
+    }
+}
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgEvtSharedData {
-    evt_dect: *const NgEvtData,
+    evt_dect: *const *const NgEvtData,
     num_steps: c_int,
 }
+impl NgEvtSharedData {
+    pub unsafe fn to_pk(self) -> PkEvtSharedData {
+        let evt_slice = std::slice::from_raw_parts(self.evt_dect, self.num_steps as usize);
+        // create vec containing 'num_steps' number of PkEvtData
+        let mut pkevtdata = Vec::<PkEvtData>::with_capacity(self.num_steps as usize);
+        for item in evt_slice.iter() {
+            // create native PkEvtData and store into vec
+            pkevtdata.push((*(*item)).to_pk());
+        }
+        PkEvtSharedData {
+            evt_dect: pkevtdata,
+            num_steps: self.num_steps,
+        }
+    }
+}
 #[derive(Copy, Clone, Debug)]
 #[repr(C)]
 pub struct NgComplex {
@@ -33,7 +60,8 @@ pub struct NgVectorinfo {
 }
 impl NgVectorinfo {
     pub unsafe fn to_pk(self) -> PkVectorinfo {
-        let (real, comp) = match self.v_type {
+        // real or complex is given by the flag (VF_REAL, VF_COMPLEX), v_type is the vector type (e.g. time, voltage)
+        let (real, comp) = match self.v_flag & 0b11 {
             1 => {
                 // real
                 let cvec = std::slice::from_raw_parts(self.v_realdata, self.v_length as usize);
@@ -0,0 +1,403 @@
+//! Ngspice rawfiles
+//!
+//! Reads and writes the rawfiles produced by `ngspice -b -r` and the `write` command, in ascii or binary encoding.
+//! A rawfile may hold several plots, each read into a `SimPlot`.
+
+use std::io::Write;
+use std::path::Path;
+
+use crate::{SimData, SimPlot, SimUnit, SimVector};
+
+/// Vector type names used in rawfiles, indexed by ngspice vector type (enum simvar_type)
+const TYPE_NAMES: [&str; 23] = [
+    "notype",
+    "time",
+    "frequency",
+    "voltage",
+    "current",
+    "voltage-density",
+    "current-density",
+    "sqr-voltage-density",
+    "sqr-current-density",
+    "sqr-voltage",
+    "sqr-current",
+    "pole",
+    "zero",
+    "s-param",
+    "temp-sweep",
+    "res-sweep",
+    "impedance",
+    "admittance",
+    "power",
+    "phase",
+    "decibel",
+    "capacitance",
+    "charge",
+];
+
+fn unit_from_name(name: &str) -> SimUnit {
+    match TYPE_NAMES.iter().position(|n| *n == name) {
+        Some(i) => SimUnit::from_stype(i as i32),
+        None => SimUnit::None,
+    }
+}
+
+fn unit_name(unit: SimUnit) -> &'static str {
+    let stype = match unit {
+        SimUnit::None => 0,
+        SimUnit::Time => 1,
+        SimUnit::Frequency => 2,
+        SimUnit::Voltage => 3,
+        SimUnit::Current => 4,
+        SimUnit::Other(i) => i,
+    };
+    TYPE_NAMES.get(stype as usize).copied().unwrap_or("notype")
+}
+
+#[derive(Debug)]
+pub enum RawfileError {
+    /// rawfile could not be read or written
+    Io(std::io::Error),
+    /// rawfile is malformed, with the line number (1-based) at which parsing failed
+    Format(usize, String),
+}
+
+impl std::fmt::Display for RawfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RawfileError::Io(e) => write!(f, "{}", e),
+            RawfileError::Format(line, msg) => write!(f, "rawfile line {}: {}", line, msg),
+        }
+    }
+}
+
+impl std::error::Error for RawfileError {}
+
+impl From<std::io::Error> for RawfileError {
+    fn from(e: std::io::Error) -> Self {
+        RawfileError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Encoding of the values of a rawfile
+pub enum RawEncoding {
+    Ascii,
+    Binary,
+}
+
+/// Cursor over the bytes of a rawfile, tracking the line number for error messages
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn err<T>(&self, msg: impl Into<String>) -> Result<T, RawfileError> {
+        Err(RawfileError::Format(self.line, msg.into()))
+    }
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+    /// returns the next line, without line terminator
+    fn line(&mut self) -> Option<String> {
+        if self.at_end() {
+            return None;
+        }
+        let rest = &self.bytes[self.pos..];
+        let len = rest.iter().position(|b| *b == b'\n').unwrap_or(rest.len());
+        self.pos += (len + 1).min(rest.len());
+        self.line += 1;
+        Some(
+            String::from_utf8_lossy(&rest[..len])
+                .trim_end_matches('\r')
+                .to_string(),
+        )
+    }
+    /// returns the next n little-endian doubles
+    fn doubles(&mut self, n: usize) -> Result<Vec<f64>, RawfileError> {
+        let len = n * 8;
+        if self.bytes.len() - self.pos < len {
+            return self.err("binary values end early");
+        }
+        let values = self.bytes[self.pos..self.pos + len]
+            .chunks_exact(8)
+            .map(|c| f64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+            .collect();
+        self.pos += len;
+        Ok(values)
+    }
+}
+
+/// Header of a plot in a rawfile
+struct Header {
+    plotname: String,
+    complex: bool,
+    points: usize,
+    /// name and unit of each variable
+    vars: Vec<(String, SimUnit)>,
+}
+
+fn parse_header(r: &mut Reader) -> Result<Option<(Header, RawEncoding)>, RawfileError> {
+    let mut plotname = String::new();
+    let mut complex = false;
+    let mut nvars = None;
+    let mut points = None;
+    let mut vars = vec![];
+    let mut reading_vars = false;
+    loop {
+        let line = match r.line() {
+            Some(line) => line,
+            // trailing blank lines after the last plot
+            None if nvars.is_none() => return Ok(None),
+            None => return r.err("header ends without values"),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) if !reading_vars || !key.starts_with(char::is_whitespace) => {
+                (key.trim().to_ascii_lowercase(), value.trim())
+            }
+            _ if reading_vars => {
+                // variable line, e.g. `\t0\ttime\ttime`
+                let mut fields = line.split_whitespace();
+                let (name, unit) = match (fields.next(), fields.next(), fields.next()) {
+                    (Some(_), Some(name), Some(unit)) => (name.to_string(), unit_from_name(unit)),
+                    _ => return r.err("malformed variable"),
+                };
+                vars.push((name, unit));
+                continue;
+            }
+            _ => return r.err(format!("unexpected line: {}", line)),
+        };
+        reading_vars = false;
+        match key.as_str() {
+            "plotname" => plotname = value.to_string(),
+            "flags" => complex = value.to_ascii_lowercase().contains("complex"),
+            "no. variables" => match value.parse::<usize>() {
+                Ok(n) => nvars = Some(n),
+                Err(_) => return r.err("invalid number of variables"),
+            },
+            "no. points" => match value.parse::<usize>() {
+                Ok(n) => points = Some(n),
+                Err(_) => return r.err("invalid number of points"),
+            },
+            "variables" => reading_vars = true,
+            "values" | "binary" => {
+                let encoding = if key == "binary" {
+                    RawEncoding::Binary
+                } else {
+                    RawEncoding::Ascii
+                };
+                let (nvars, points) = match (nvars, points) {
+                    (Some(nvars), Some(points)) => (nvars, points),
+                    _ => return r.err("number of variables or points missing"),
+                };
+                if vars.len() != nvars {
+                    return r.err(format!(
+                        "expected {} variables, found {}",
+                        nvars,
+                        vars.len()
+                    ));
+                }
+                let header = Header {
+                    plotname,
+                    complex,
+                    points,
+                    vars,
+                };
+                return Ok(Some((header, encoding)));
+            }
+            // title, date, command, option, dimensions etc. are not used
+            _ => {}
+        }
+    }
+}
+
+fn parse_ascii_value(r: &Reader, s: &str, complex: bool) -> Result<(f64, f64), RawfileError> {
+    let parsed = if complex {
+        s.split_once(',')
+            .and_then(|(re, im)| Some((re.parse().ok()?, im.parse().ok()?)))
+    } else {
+        s.parse().ok().map(|re| (re, 0.0))
+    };
+    match parsed {
+        Some(v) => Ok(v),
+        None => r.err(format!("invalid value: {}", s)),
+    }
+}
+
+/// returns the values of each variable, as (real, imaginary) pairs
+fn parse_values(
+    r: &mut Reader,
+    header: &Header,
+    encoding: RawEncoding,
+) -> Result<Vec<Vec<(f64, f64)>>, RawfileError> {
+    let nvars = header.vars.len();
+    // the point count comes from the file: do not allocate more than the values left could fill
+    let remaining = r.bytes.len() - r.pos;
+    let mut values = vec![Vec::with_capacity(header.points.min(remaining / 8)); nvars];
+    match encoding {
+        RawEncoding::Binary => {
+            let width = if header.complex { 2 } else { 1 };
+            let len = header
+                .points
+                .checked_mul(nvars * width * 8)
+                .ok_or_else(|| RawfileError::Format(r.line, String::from("too many points")))?;
+            if len > remaining {
+                return r.err("binary values end early");
+            }
+            for _ in 0..header.points {
+                let point = r.doubles(nvars * width)?;
+                for (i, v) in point.chunks_exact(width).enumerate() {
+                    values[i].push((v[0], v.get(1).copied().unwrap_or(0.0)));
+                }
+            }
+        }
+        RawEncoding::Ascii => {
+            // each point is its index followed by the value of every variable, separated by whitespace
+            let mut tokens: Vec<String> = vec![];
+            let needed = header
+                .points
+                .checked_mul(nvars + 1)
+                .ok_or_else(|| RawfileError::Format(r.line, String::from("too many points")))?;
+            let mut consumed = 0;
+            while consumed < needed {
+                if tokens.is_empty() {
+                    match r.line() {
+                        Some(line) => {
+                            tokens = line.split_whitespace().rev().map(String::from).collect()
+                        }
+                        None => return r.err("ascii values end early"),
+                    }
+                    continue;
+                }
+                let token = tokens.pop().unwrap_or_default();
+                let var = consumed % (nvars + 1);
+                if var > 0 {
+                    let v = parse_ascii_value(r, &token, header.complex)?;
+                    values[var - 1].push(v);
+                }
+                consumed += 1;
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Parses every plot of a rawfile, in either encoding
+pub fn parse_rawfile(bytes: &[u8]) -> Result<Vec<SimPlot>, RawfileError> {
+    let mut r = Reader {
+        bytes,
+        pos: 0,
+        line: 0,
+    };
+    let mut plots = vec![];
+    while let Some((header, encoding)) = parse_header(&mut r)? {
+        let values = parse_values(&mut r, &header, encoding)?;
+        // the first variable is the scale
+        let scale = header.vars.first().map(|(name, _)| name.clone());
+        let vecs = header
+            .vars
+            .into_iter()
+            .zip(values)
+            .map(|((name, unit), values)| SimVector {
+                name,
+                unit,
+                data: if header.complex {
+                    SimData::Complex(
+                        values
+                            .into_iter()
+                            .map(|(re, im)| num::Complex::new(re, im))
+                            .collect(),
+                    )
+                } else {
+                    SimData::Real(values.into_iter().map(|(re, _)| re).collect())
+                },
+                scale: None,
+            })
+            .collect();
+        plots.push(SimPlot::new(header.plotname, vecs, scale.as_deref()));
+    }
+    Ok(plots)
+}
+
+/// Reads every plot of the rawfile at path
+pub fn read_rawfile(path: &Path) -> Result<Vec<SimPlot>, RawfileError> {
+    parse_rawfile(&std::fs::read(path)?)
+}
+
+/// Writes plots as a rawfile. The scale of each plot, if any, is written as its first variable.
+/// A plot is written as complex if any of its vectors is complex.
+pub fn write_raw<W: Write>(
+    w: &mut W,
+    plots: &[SimPlot],
+    encoding: RawEncoding,
+) -> std::io::Result<()> {
+    for plot in plots {
+        let mut vecs: Vec<&SimVector> = plot.vecs.iter().collect();
+        // scale first, as expected by readers of rawfiles
+        if let Some(i) = vecs.iter().position(|v| {
+            v.scale.is_none() && plot.vecs.iter().any(|o| o.scale.as_ref() == Some(&v.name))
+        }) {
+            let scale = vecs.remove(i);
+            vecs.insert(0, scale);
+        }
+        let complex = vecs.iter().any(|v| matches!(v.data, SimData::Complex(_)));
+        let points = vecs.iter().map(|v| v.data.len()).max().unwrap_or(0);
+        writeln!(w, "Title: {}", plot.name)?;
+        writeln!(w, "Date: ")?;
+        writeln!(w, "Plotname: {}", plot.name)?;
+        writeln!(w, "Flags: {}", if complex { "complex" } else { "real" })?;
+        writeln!(w, "No. Variables: {}", vecs.len())?;
+        writeln!(w, "No. Points: {}", points)?;
+        writeln!(w, "Variables:")?;
+        for (i, v) in vecs.iter().enumerate() {
+            writeln!(w, "\t{}\t{}\t{}", i, v.name, unit_name(v.unit))?;
+        }
+        // vectors shorter than the plot are padded with zeros
+        let value = |v: &SimVector, i: usize| match &v.data {
+            SimData::Real(d) => (d.get(i).copied().unwrap_or(0.0), 0.0),
+            SimData::Complex(d) => d.get(i).map_or((0.0, 0.0), |c| (c.re, c.im)),
+        };
+        match encoding {
+            RawEncoding::Ascii => {
+                writeln!(w, "Values:")?;
+                for i in 0..points {
+                    write!(w, " {}", i)?;
+                    for v in &vecs {
+                        let (re, im) = value(v, i);
+                        if complex {
+                            writeln!(w, "\t{:e},{:e}", re, im)?;
+                        } else {
+                            writeln!(w, "\t{:e}", re)?;
+                        }
+                    }
+                    writeln!(w)?;
+                }
+            }
+            RawEncoding::Binary => {
+                writeln!(w, "Binary:")?;
+                for i in 0..points {
+                    for v in &vecs {
+                        let (re, im) = value(v, i);
+                        w.write_all(&re.to_le_bytes())?;
+                        if complex {
+                            w.write_all(&im.to_le_bytes())?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes plots as a rawfile at path
+pub fn write_rawfile(path: &Path, plots: &[SimPlot], encoding: RawEncoding) -> std::io::Result<()> {
+    let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+    write_raw(&mut w, plots, encoding)?;
+    w.flush()
+}
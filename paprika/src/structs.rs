@@ -67,3 +67,117 @@ pub struct PkVectorinfo {
     pub compdata: Option<Vec<num::Complex<f64>>>,
     pub length: i32,
 }
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Struct known as evt_data in Ngspice User's Manual
+/// A single data point of an event node
+pub struct PkEvtData {
+    pub dcop: i32,
+    /// simulation time
+    pub step: f64,
+    /// one of 0s, 1s, Us, 0r, 1r, Ur, 0z, 1z, Uz, 0u, 1u, Uu for digital nodes
+    pub node_value: String,
+}
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Struct known as evt_shared_data in Ngspice User's Manual
+/// All data points of an event node
+pub struct PkEvtSharedData {
+    pub evt_dect: Vec<PkEvtData>,
+    pub num_steps: i32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Unit of a vector, from its type known as stype or v_type in Ngspice User's Manual
+pub enum SimUnit {
+    None,
+    Time,
+    Frequency,
+    Voltage,
+    Current,
+    /// any other ngspice vector type, e.g. noise spectral density
+    Other(i32),
+}
+
+impl SimUnit {
+    /// Maps the ngspice vector type (enum simvar_type) to its unit
+    pub fn from_stype(stype: i32) -> Self {
+        match stype {
+            0 => SimUnit::None,
+            1 => SimUnit::Time,
+            2 => SimUnit::Frequency,
+            3 => SimUnit::Voltage,
+            4 => SimUnit::Current,
+            _ => SimUnit::Other(stype),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Data of a vector, real or complex
+pub enum SimData {
+    Real(Vec<f64>),
+    Complex(Vec<num::Complex<f64>>),
+}
+
+impl SimData {
+    pub fn len(&self) -> usize {
+        match self {
+            SimData::Real(v) => v.len(),
+            SimData::Complex(v) => v.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Returns the real data, or the real part of the complex data
+    pub fn real(&self) -> Vec<f64> {
+        match self {
+            SimData::Real(v) => v.clone(),
+            SimData::Complex(v) => v.iter().map(|c| c.re).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Named vector of a plot
+pub struct SimVector {
+    pub name: String,
+    pub unit: SimUnit,
+    pub data: SimData,
+    /// name of the scale vector this vector is plotted against, none for the scale itself or if the plot has no scale
+    pub scale: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+/// Every vector of a plot, e.g. the results of a simulation
+pub struct SimPlot {
+    /// plot name, e.g. tran1
+    pub name: String,
+    pub vecs: Vec<SimVector>,
+}
+
+impl SimPlot {
+    /// Builds a plot from its vectors, setting the scale of every other vector to the vector named scale, if any.
+    pub fn new(name: String, mut vecs: Vec<SimVector>, scale: Option<&str>) -> Self {
+        if let Some(scale) = scale.filter(|s| vecs.iter().any(|v| v.name == *s)) {
+            for v in vecs.iter_mut().filter(|v| v.name != scale) {
+                v.scale = Some(scale.to_string());
+            }
+        }
+        SimPlot { name, vecs }
+    }
+    /// Returns the vector named name, if any
+    pub fn get(&self, name: &str) -> Option<&SimVector> {
+        self.vecs.iter().find(|v| v.name == name)
+    }
+    /// Returns the scale vector of vec, if any
+    pub fn scale_of(&self, vec: &SimVector) -> Option<&SimVector> {
+        vec.scale.as_deref().and_then(|s| self.get(s))
+    }
+}
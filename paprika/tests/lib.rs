@@ -1,6 +1,6 @@
 use std::collections::VecDeque;
-use std::ffi::OsStr;
-use std::sync::{Arc, RwLock};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
 
 use paprika::*;
 
@@ -49,29 +49,683 @@ impl paprika::PkSpiceManager for Manager {
     }
 }
 
+/// path of the fake sharedspice library, built next to the test executable as a dev-dependency
+fn fakespice_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().join(format!(
+        "{}fakespice{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+/// a private instance of the fake sharedspice library - library state is global, and tests run in parallel
+fn fakespice_for<T>(id: i32) -> PkSpice<T> {
+    let dir = std::env::temp_dir().join(format!("paprika_test_fakespice_{}", id));
+    std::fs::create_dir_all(&dir).unwrap();
+    PkSpice::new_copy(fakespice_path().as_os_str(), &dir, id).unwrap()
+}
+
+fn fakespice(id: i32) -> PkSpice {
+    fakespice_for(id)
+}
+
 #[test]
+#[allow(deprecated)]
 fn test_cmd_echo() {
-    let mut spice = PkSpice::<Manager>::new(OsStr::new("ngspice.dll")).unwrap();
+    let mut spice = fakespice_for::<Manager>(1);
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
     let manager = Arc::new(Manager::new(buf.clone()));
 
     spice.init(Some(manager)); // register
 
-    spice.command("echo echo command");
+    spice.command("echo echo command").unwrap();
     let s = (*buf.write().unwrap()).pop_back().unwrap();
     assert_eq!(s, "stdout echo command");
-    spice.command("quit");
-} // cannot run tests in parallel
+    spice.command("quit").unwrap();
+}
 
 #[test]
 fn test_dcop() {
-    let mut spice = PkSpice::<Manager>::new(OsStr::new("ngspice.dll")).unwrap();
+    let mut spice = fakespice(2);
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let manager = Arc::new(Mutex::new(Manager::new(buf.clone())));
+
+    spice.init_manager(manager); // register
+
+    spice.command("source dcop.cir").unwrap();
+    spice.command("op").unwrap();
+    spice.command("quit").unwrap();
+}
+
+#[test]
+fn test_command_errors() {
+    let spice = fakespice(10);
+    assert!(matches!(
+        spice.command("bg_run"),
+        Err(PkSpiceError::BackgroundCommand(_))
+    ));
+    assert!(matches!(
+        spice.command("nonsense"),
+        Err(PkSpiceError::CommandFailed(c)) if c == "nonsense"
+    ));
+    spice.command("echo ok").unwrap();
+    // strings with NUL bytes cannot be passed to ngspice
+    assert!(matches!(
+        spice.command("echo a\0b"),
+        Err(PkSpiceError::NulByte(_))
+    ));
+    assert!(matches!(
+        spice.circ(&[String::from("title"), String::from("R1 a\0 0 1k")]),
+        Err(PkSpiceError::NulByte(_))
+    ));
+    assert!(matches!(
+        spice.get_all_vecs("const\0"),
+        Err(PkSpiceError::NulByte(_))
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_init_shared_manager() {
+    let mut spice = fakespice_for::<Manager>(9);
     let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
     let manager = Arc::new(Manager::new(buf.clone()));
+    // the manager cannot be mutated by callbacks while shared
+    assert_eq!(spice.init(Some(manager.clone())), 1);
+    spice.command("echo not delivered").unwrap();
+    assert!(buf.read().unwrap().is_empty());
+    assert_eq!(spice.init(None), 0);
+}
 
-    spice.init(Some(manager)); // register
+#[test]
+fn test_parse_stat() {
+    assert_eq!(parse_stat("tran: 34.5%"), Some(("tran", 34.5)));
+    assert_eq!(
+        parse_stat("Source Stepping: 100%"),
+        Some(("Source Stepping", 100.0))
+    );
+    assert_eq!(parse_stat("--ready--"), None);
+    assert_eq!(parse_stat("tran: done"), None);
+}
+
+#[test]
+fn test_event_dispatch() {
+    let buf = Arc::new(RwLock::new(VecDeque::<String>::with_capacity(10)));
+    let mut manager = Manager::new(buf.clone());
+
+    PkEvent::Char {
+        msg: String::from("stdout hello"),
+        id: 0,
+    }
+    .dispatch(&mut manager);
+    assert_eq!(buf.read().unwrap().back().unwrap(), "stdout hello");
+
+    PkEvent::Exit {
+        status: 0,
+        is_immediate: false,
+        is_quit: true,
+        id: 0,
+    }
+    .dispatch(&mut manager);
+    assert!(manager.quit_flag);
+}
+
+#[test]
+fn test_parse_spice_number() {
+    assert_eq!(parse_spice_number("10"), Some(10.0));
+    assert_eq!(parse_spice_number("2e-3"), Some(2e-3));
+    assert_eq!(parse_spice_number("1.5Meg"), Some(1.5e6));
+    assert_eq!(parse_spice_number("5ms"), Some(5e-3));
+    assert_eq!(parse_spice_number(" 10u "), Some(10e-6));
+    assert_eq!(parse_spice_number("3k"), Some(3e3));
+    assert_eq!(parse_spice_number("u10"), None);
+    assert_eq!(parse_spice_number(""), None);
+}
+
+#[test]
+fn test_parse_version() {
+    assert_eq!(
+        parse_version("stdout ******\nstdout ** ngspice-39 : Circuit level simulation program"),
+        Some(39)
+    );
+    assert_eq!(
+        parse_version("stdout ** ngspice-30plus shared library"),
+        Some(30)
+    );
+    assert_eq!(parse_version("stdout echo"), None);
+}
+
+#[test]
+fn test_locate_sharedspice_config() {
+    let dir = std::env::temp_dir().join("paprika_test_locate");
+    let env_dir = dir.join("env");
+    std::fs::create_dir_all(&env_dir).unwrap();
+    let lib = dir.join(sharedspice_names()[0]);
+    let env_lib = env_dir.join(sharedspice_names()[0]);
+    std::fs::write(&lib, b"").unwrap();
+    std::fs::write(&env_lib, b"").unwrap();
+
+    // no other test reads the environment variable, so it is set here rather than assumed unset
+    std::env::remove_var(SHAREDSPICE_ENV);
+    // config may name the library directory or the library itself
+    let candidates = sharedspice_candidates(Some(&dir));
+    assert!(candidates.contains(&lib));
+    assert_eq!(sharedspice_candidates(Some(&lib)).first(), Some(&lib));
+    assert_eq!(locate_sharedspice(Some(&dir)), Some(lib.clone()));
+
+    // the environment variable takes precedence over config
+    std::env::set_var(SHAREDSPICE_ENV, &env_dir);
+    assert_eq!(sharedspice_candidates(Some(&lib)).first(), Some(&env_lib));
+    assert_eq!(locate_sharedspice(Some(&dir)), Some(env_lib));
+    std::env::remove_var(SHAREDSPICE_ENV);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_analysis_command() {
+    assert_eq!(Analysis::Op.card().unwrap(), ".op");
+    let ac = Analysis::Ac(FreqSweep {
+        scale: SweepScale::Dec,
+        points: 10,
+        fstart: 1.0,
+        fstop: 1e6,
+    });
+    assert_eq!(ac.command().unwrap(), "ac dec 10 1e0 1e6");
+    let tran = Analysis::Tran {
+        tstep: 1e-6,
+        tstop: 1e-3,
+        tstart: None,
+        tmax: Some(1e-7),
+        uic: true,
+    };
+    assert_eq!(tran.command().unwrap(), "tran 1e-6 1e-3 0e0 1e-7 uic");
+    let dc = Analysis::Dc {
+        src: String::from("v1"),
+        start: 0.0,
+        stop: 5.0,
+        step: 0.5,
+        nested: Some(DcSweep {
+            src: String::from("i1"),
+            start: 0.0,
+            stop: 1e-3,
+            step: 5e-4,
+        }),
+    };
+    assert_eq!(dc.command().unwrap(), "dc v1 0e0 5e0 5e-1 i1 0e0 1e-3 5e-4");
+    assert_eq!(dc.sweep_points(), Some(11));
+    let dc = Analysis::Dc {
+        src: String::from("v1"),
+        start: 0.0,
+        stop: 5.0,
+        step: -0.1,
+        nested: None,
+    };
+    assert_eq!(dc.command(), Err(AnalysisError::Step));
+    let ac = Analysis::Ac(FreqSweep {
+        scale: SweepScale::Lin,
+        points: 0,
+        fstart: 1.0,
+        fstop: 1.0,
+    });
+    assert_eq!(ac.validate(), Err(AnalysisError::ZeroPoints));
+    // linear sweeps may start from dc, logarithmic ones may not
+    let sweep = |scale| FreqSweep {
+        scale,
+        points: 100,
+        fstart: 0.0,
+        fstop: 1e3,
+    };
+    let ac = Analysis::Ac(sweep(SweepScale::Lin));
+    assert_eq!(ac.card().unwrap(), ".ac lin 100 0e0 1e3");
+    assert_eq!(
+        Analysis::Ac(sweep(SweepScale::Dec)).validate(),
+        Err(AnalysisError::NotPositive("fstart"))
+    );
+    assert_eq!(
+        Analysis::Ac(sweep(SweepScale::Oct)).validate(),
+        Err(AnalysisError::NotPositive("fstart"))
+    );
+    let tran = Analysis::Tran {
+        tstep: 1e-6,
+        tstop: 1e-3,
+        tstart: Some(1e-3),
+        tmax: None,
+        uic: false,
+    };
+    let e = tran.validate().unwrap_err();
+    assert_eq!(e, AnalysisError::NotLess("tstart", "tstop"));
+    assert_eq!(e.to_string(), "tstart must be less than tstop");
+    let disto = |f2overf1: f64| Analysis::Disto {
+        sweep: FreqSweep {
+            scale: SweepScale::Dec,
+            points: 10,
+            fstart: 1e3,
+            fstop: 1e6,
+        },
+        f2overf1: Some(f2overf1),
+    };
+    assert_eq!(disto(0.9).validate(), Ok(()));
+    assert_eq!(
+        disto(1.0).validate(),
+        Err(AnalysisError::OutOfRange("f2overf1", "(0, 1)"))
+    );
+}
 
-    spice.command("source dcop.cir");
-    spice.command("op");
-    spice.command("quit");
+#[test]
+fn test_sim_plot_scale() {
+    let vec = |name: &str, stype: i32| SimVector {
+        name: name.to_string(),
+        unit: SimUnit::from_stype(stype),
+        data: SimData::Real(vec![0.0, 1.0]),
+        scale: None,
+    };
+    let plot = SimPlot::new(
+        String::from("tran1"),
+        vec![vec("v(1)", 3), vec("time", 1), vec("i(v1)", 4)],
+        Some("time"),
+    );
+    assert_eq!(plot.get("time").unwrap().unit, SimUnit::Time);
+    assert_eq!(plot.get("time").unwrap().scale, None);
+    let v1 = plot.get("v(1)").unwrap();
+    assert_eq!(plot.scale_of(v1).unwrap().name, "time");
+
+    // the scale is not guessed from the units
+    let plot = SimPlot::new(
+        String::from("tran1"),
+        vec![vec("v(1)", 3), vec("time", 1)],
+        None,
+    );
+    assert_eq!(plot.get("v(1)").unwrap().scale, None);
+    let plot = SimPlot::new(
+        String::from("dc1"),
+        vec![vec("v(1)", 3), vec("v-sweep", 3)],
+        Some("v-sweep"),
+    );
+    assert_eq!(plot.get("v(1)").unwrap().scale.as_deref(), Some("v-sweep"));
+}
+
+#[test]
+fn test_rawfile_ascii() {
+    let raw = "Title: rc\nDate: Thu Jan  1 00:00:00  2023\nPlotname: AC Analysis\nFlags: complex\nNo. Variables: 2\nNo. Points: 2\nVariables:\n\t0\tfrequency\tfrequency grid=3\n\t1\tv(out)\tvoltage\nValues:\n 0\t1.0e0,0.0e0\n\t1.0e0,-1.0e-1\n\n 1\t1.0e1,0.0e0\n\t5.0e-1,-5.0e-1\n\n";
+    let plots = parse_rawfile(raw.as_bytes()).unwrap();
+    assert_eq!(plots.len(), 1);
+    let v = plots[0].get("v(out)").unwrap();
+    assert_eq!(v.unit, SimUnit::Voltage);
+    assert_eq!(v.scale.as_deref(), Some("frequency"));
+    assert_eq!(
+        v.data,
+        SimData::Complex(vec![
+            num::Complex::new(1.0, -0.1),
+            num::Complex::new(0.5, -0.5)
+        ])
+    );
+}
+
+#[test]
+fn test_rawfile_roundtrip() {
+    let plot = SimPlot::new(
+        String::from("Transient Analysis"),
+        vec![
+            SimVector {
+                name: String::from("v(1)"),
+                unit: SimUnit::Voltage,
+                data: SimData::Real(vec![0.0, 0.5, 1.0]),
+                scale: None,
+            },
+            SimVector {
+                name: String::from("time"),
+                unit: SimUnit::Time,
+                data: SimData::Real(vec![0.0, 1e-6, 2e-6]),
+                scale: None,
+            },
+        ],
+        Some("time"),
+    );
+    for encoding in [RawEncoding::Ascii, RawEncoding::Binary] {
+        let mut buf = vec![];
+        write_raw(&mut buf, &[plot.clone(), plot.clone()], encoding).unwrap();
+        let plots = parse_rawfile(&buf).unwrap();
+        assert_eq!(plots.len(), 2);
+        // scale is written first
+        assert_eq!(plots[1].vecs[0].name, "time");
+        assert_eq!(plots[1].get("v(1)"), plot.get("v(1)"));
+    }
+}
+
+#[test]
+fn test_rawfile_bad_point_count() {
+    let raw = |points: &str, values: &str| {
+        format!(
+            "Title: rc\nPlotname: Transient Analysis\nFlags: real\nNo. Variables: 2\nNo. Points: {}\nVariables:\n\t0\ttime\ttime\n\t1\tv(out)\tvoltage\n{}",
+            points, values
+        )
+    };
+    let ascii = "Values:\n 0\t0.0e0\n\t1.0e0\n";
+    let mut binary = b"Binary:\n".to_vec();
+    for v in [0.0f64, 1.0] {
+        binary.extend(v.to_le_bytes());
+    }
+    for points in ["18446744073709551615", "4000000000000", "2"] {
+        // the point count overflows, or is more than the values given
+        let ascii = raw(points, ascii);
+        assert!(matches!(
+            parse_rawfile(ascii.as_bytes()),
+            Err(RawfileError::Format(..))
+        ));
+        let mut bytes = raw(points, "").into_bytes();
+        bytes.extend(&binary);
+        assert!(matches!(
+            parse_rawfile(&bytes),
+            Err(RawfileError::Format(..))
+        ));
+    }
+}
+
+#[test]
+fn test_fakespice_bg_tran() {
+    let mut spice = fakespice(3);
+    assert_eq!(spice.version(), Some(40));
+    let rx = spice.init_channel();
+    let lines: Vec<String> = [
+        "rc",
+        "V1 in 0 1",
+        "R1 in out 1k",
+        "C1 out 0 1u",
+        ".tran 1u 10u",
+        ".end",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    spice.circ(&lines).unwrap();
+    spice.bg_run().unwrap();
+
+    let mut data = 0;
+    loop {
+        match rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap() {
+            PkEvent::Data { vecvalues, .. } => {
+                assert_eq!(vecvalues.vecsa[0].name, "time");
+                data += 1;
+            }
+            PkEvent::BgState { is_fin: true, .. } => break,
+            _ => {}
+        }
+    }
+    assert_eq!(data, 11);
+
+    let plot = spice.get_plot(&spice.get_cur_plot());
+    assert_eq!(plot.name, "tran1");
+    let out = plot.get("out").unwrap();
+    assert_eq!(out.unit, SimUnit::Voltage);
+    assert_eq!(out.scale.as_deref(), Some("time"));
+    assert_eq!(out.data.len(), 11);
+    assert_eq!(spice.get_vec_info("tran1.out").unwrap().name, "out");
+    assert!(spice.get_vec_info("tran1.nothing").is_none());
+}
+
+/// waits for the background thread of spice to finish
+fn wait_bg(rx: &std::sync::mpsc::Receiver<PkEvent>) {
+    loop {
+        if let PkEvent::BgState { is_fin: true, .. } =
+            rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap()
+        {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_fakespice_breakpoint_halt_resume() {
+    let mut spice = fakespice(8);
+    let rx = spice.init_channel();
+    let rc = |tstep: &str, tstop: &str| -> Vec<String> {
+        vec![
+            String::from("rc"),
+            String::from("V1 in 0 1"),
+            String::from("R1 in out 1k"),
+            String::from("C1 out 0 1u"),
+            format!(".tran {} {}", tstep, tstop),
+            String::from(".end"),
+        ]
+    };
+    let times = |spice: &PkSpice| -> Vec<f64> {
+        let plot = spice.get_plot(&spice.get_cur_plot());
+        plot.get("time").unwrap().data.real()
+    };
+
+    // no circuit is loaded yet
+    assert!(matches!(
+        spice.set_breakpoint(4e-6),
+        Err(PkSpiceError::BreakpointFailed(_))
+    ));
+    // the simulation stops at the breakpoint, keeping the point reaching it
+    spice.circ(&rc("1u", "10u")).unwrap();
+    spice.set_breakpoint(4e-6).unwrap();
+    spice.bg_run().unwrap();
+    wait_bg(&rx);
+    let time = times(&spice);
+    assert!(time.len() < 11);
+    assert!(*time.last().unwrap() >= 4e-6);
+    // and continues to the end
+    spice.bg_resume().unwrap();
+    wait_bg(&rx);
+    assert_eq!(times(&spice).len(), 11);
+    assert_eq!(spice.get_cur_plot(), "tran1");
+
+    // a halted simulation continues where it stopped
+    spice.circ(&rc("1n", "100u")).unwrap();
+    spice.bg_run().unwrap();
+    spice.bg_halt().unwrap();
+    assert!(!spice.is_running());
+    wait_bg(&rx);
+    let halted = times(&spice).len();
+    assert!(halted < 100_001);
+    spice.bg_resume().unwrap();
+    wait_bg(&rx);
+    let time = times(&spice);
+    assert_eq!(time.len(), 100_001);
+    assert!(time.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_fakespice_drop_after_finish() {
+    // dropped as soon as the background thread reports finishing, while it is still returning from the callback
+    for id in 100..150 {
+        let mut spice = fakespice(id);
+        let rx = spice.init_channel();
+        let lines: Vec<String> = ["rc", "V1 in 0 1", "R1 in out 1k", ".tran 1u 2u", ".end"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        spice.circ(&lines).unwrap();
+        spice.bg_run().unwrap();
+        wait_bg(&rx);
+    }
+}
+
+/// external source values for `init_sync`, recording every call
+struct Sync {
+    /// (time, source name, id) of every GetVSRCData call
+    calls: Arc<Mutex<Vec<(f64, String, i32)>>>,
+    /// number of GetSyncData calls
+    syncs: Arc<Mutex<usize>>,
+}
+impl PkSpiceSync for Sync {
+    fn cb_get_vsrc(&mut self, time: f64, node: &str, id: i32) -> f64 {
+        self.calls
+            .lock()
+            .unwrap()
+            .push((time, node.to_string(), id));
+        time * 1e3
+    }
+    fn cb_get_isrc(&mut self, _time: f64, _node: &str, _id: i32) -> f64 {
+        0.0
+    }
+    fn cb_get_sync(
+        &mut self,
+        _time: f64,
+        _delta: &mut f64,
+        _old_delta: f64,
+        _redostep: i32,
+        _id: i32,
+        _location: i32,
+    ) -> i32 {
+        *self.syncs.lock().unwrap() += 1;
+        0
+    }
+}
+
+#[test]
+fn test_fakespice_init_sync() {
+    let mut spice = fakespice(6);
+    let rx = spice.init_channel();
+    let calls = Arc::new(Mutex::new(vec![]));
+    let syncs = Arc::new(Mutex::new(0));
+    spice.init_sync(
+        Some(Box::new(Sync {
+            calls: calls.clone(),
+            syncs: syncs.clone(),
+        })),
+        16,
+    );
+    assert_eq!(spice.id(), 16);
+    let lines: Vec<String> = [
+        "external",
+        "V1 in 0 dc 0 external",
+        "R1 in 0 1k",
+        ".tran 1u 10u",
+        ".end",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    spice.circ(&lines).unwrap();
+    spice.command("run").unwrap();
+
+    assert_eq!(*syncs.lock().unwrap(), 11);
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 11);
+    assert!(calls.iter().all(|(_, node, id)| node == "v1" && *id == 16));
+    // every callback reports the id set by init_sync
+    assert!(rx.try_iter().all(|event| match event {
+        PkEvent::Data { id, .. } | PkEvent::Stat { id, .. } | PkEvent::Init { id, .. } => id == 16,
+        _ => true,
+    }));
+    // the external source drives its node
+    let plot = spice.get_plot(&spice.get_cur_plot());
+    let time = plot.get("time").unwrap().data.real();
+    let vin = plot.get("in").unwrap().data.real();
+    for (t, v) in time.iter().zip(&vin) {
+        assert!((v - t * 1e3).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_fakespice_parallel_copies() {
+    // each instance has its own copy of the library, and so its own circuit and background thread
+    let mut spices = [fakespice(4), fakespice(5)];
+    let mut receivers = vec![];
+    for (spice, tstop) in spices.iter_mut().zip(["10u", "20u"]) {
+        let id = spice.id();
+        let rx = spice.init_channel();
+        spice.init_sync(None, id);
+        let lines: Vec<String> = vec![
+            format!("copy {}", id),
+            String::from("V1 in 0 1"),
+            String::from("R1 in out 1k"),
+            String::from("C1 out 0 1u"),
+            format!(".tran 1u {}", tstop),
+            String::from(".end"),
+        ];
+        spice.circ(&lines).unwrap();
+        receivers.push(rx);
+    }
+    for spice in &spices {
+        spice.bg_run().unwrap();
+    }
+    for ((spice, rx), points) in spices.iter().zip(&receivers).zip([11, 21]) {
+        let mut data = 0;
+        loop {
+            match rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap() {
+                PkEvent::Data { id, .. } => {
+                    assert_eq!(id, spice.id());
+                    data += 1;
+                }
+                PkEvent::BgState { is_fin: true, id } => {
+                    assert_eq!(id, spice.id());
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(data, points);
+        let plot = spice.get_plot(&spice.get_cur_plot());
+        assert_eq!(plot.name, "tran1");
+        assert_eq!(plot.get("out").unwrap().data.len(), points);
+    }
+}
+
+#[test]
+fn test_fakespice_evt_nodes() {
+    let mut spice = fakespice(7);
+    let rx = spice.init_channel();
+    assert_eq!(spice.init_evt(), 0);
+    let lines: Vec<String> = [
+        "inverter",
+        "a1 [in] [out] inv",
+        ".model inv d_inverter",
+        ".tran 1u 3u",
+        ".end",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    spice.circ(&lines).unwrap();
+    spice.command("run").unwrap();
+
+    let mut names = vec![];
+    let mut values = vec![];
+    for event in rx.try_iter() {
+        match event {
+            PkEvent::InitEvtData {
+                node_index,
+                max_index,
+                name,
+                udn_name,
+                ..
+            } => {
+                assert_eq!(max_index, 1);
+                assert_eq!(udn_name, "d");
+                names.push((node_index, name));
+            }
+            PkEvent::EvtData {
+                node_index: 1,
+                dvalue,
+                svalue,
+                ..
+            } => values.push((dvalue, svalue)),
+            _ => {}
+        }
+    }
+    assert_eq!(
+        names,
+        vec![(0, String::from("in")), (1, String::from("out"))]
+    );
+    assert_eq!(values.len(), 4);
+    assert_eq!(values[0], (1.0, String::from("1s")));
+    assert_eq!(values[1], (0.0, String::from("0s")));
+
+    assert_eq!(spice.get_all_evt_nodes(), vec!["in", "out"]);
+    // every field is read at its offset in evt_data: dcop, step, node_value
+    let info = spice.get_evt_node_info("out").unwrap().unwrap();
+    assert_eq!(info.num_steps, 4);
+    assert_eq!(info.evt_dect.len(), 4);
+    for (i, data) in info.evt_dect.iter().enumerate() {
+        assert_eq!(data.dcop, (i == 0) as i32);
+        assert!((data.step - i as f64 * 1e-6).abs() < 1e-15);
+        assert_eq!(data.node_value, values[i].1);
+    }
+    assert!(spice.get_evt_node_info("nothere").unwrap().is_none());
 }
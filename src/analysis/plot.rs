@@ -25,6 +25,10 @@ pub type PlotTrace = Vec<VSPoint>;
 #[derive(Debug, Clone)]
 pub enum ChartElement {
     PlotTrace(PlotTrace),
+    /// poles of a pole-zero analysis, drawn as crosses, red if in the right half plane
+    Poles(PlotTrace),
+    /// zeros of a pole-zero analysis, drawn as circles
+    Zeros(PlotTrace),
 }
 
 impl Default for ChartElement {
@@ -33,87 +37,92 @@ impl Default for ChartElement {
     }
 }
 
-impl PartialEq for ChartElement {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::PlotTrace(l0), Self::PlotTrace(r0)) => {
-                by_address::ByAddress(l0) == by_address::ByAddress(r0)
+impl ChartElement {
+    fn points(&self) -> &PlotTrace {
+        match self {
+            ChartElement::PlotTrace(trace)
+            | ChartElement::Poles(trace)
+            | ChartElement::Zeros(trace) => trace,
+        }
+    }
+    /// draw the element with color, except right half plane poles which are always red
+    fn draw_color(&self, vct: VCTransform, color: Color, frame: &mut Frame) {
+        let stroke = |color| Stroke {
+            width: 1.0,
+            style: stroke::Style::Solid(color),
+            line_cap: LineCap::Square,
+            ..Stroke::default()
+        };
+        // marker size on canvas
+        let dim = 4.0;
+        match self {
+            ChartElement::PlotTrace(trace) => {
+                let mut path_builder = Builder::new();
+                for vsp in trace {
+                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
+                }
+                frame.stroke(&path_builder.build(), stroke(color));
+            }
+            ChartElement::Poles(poles) => {
+                for vsp in poles {
+                    let csp = vct.transform_point(*vsp);
+                    let mut path_builder = Builder::new();
+                    path_builder.move_to(iced::Point::new(csp.x - dim, csp.y - dim));
+                    path_builder.line_to(iced::Point::new(csp.x + dim, csp.y + dim));
+                    path_builder.move_to(iced::Point::new(csp.x - dim, csp.y + dim));
+                    path_builder.line_to(iced::Point::new(csp.x + dim, csp.y - dim));
+                    let color = if vsp.x > 0.0 {
+                        Color::from_rgb(1.0, 0.3, 0.3)
+                    } else {
+                        color
+                    };
+                    frame.stroke(&path_builder.build(), stroke(color));
+                }
+            }
+            ChartElement::Zeros(zeros) => {
+                for vsp in zeros {
+                    let csp = vct.transform_point(*vsp);
+                    let c = Path::circle(iced::Point::new(csp.x, csp.y), dim);
+                    frame.stroke(&c, stroke(color));
+                }
             }
         }
     }
 }
 
+impl PartialEq for ChartElement {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+            && by_address::ByAddress(self.points()) == by_address::ByAddress(other.points())
+    }
+}
+
 impl Eq for ChartElement {}
 
 impl std::hash::Hash for ChartElement {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        match self {
-            ChartElement::PlotTrace(d) => by_address::ByAddress(d).hash(state),
-        }
+        std::mem::discriminant(self).hash(state);
+        by_address::ByAddress(self.points()).hash(state);
     }
 }
 
 impl Drawable for ChartElement {
     fn draw_persistent(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
-        match self {
-            ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(0.8, 0.8, 0.8)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
-            }
-        }
+        self.draw_color(vct, Color::from_rgb(0.8, 0.8, 0.8), frame);
     }
 
     fn draw_selected(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
-        match self {
-            ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(0.9, 0.9, 0.9)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
-            }
-        }
+        self.draw_color(vct, Color::from_rgb(0.9, 0.9, 0.9), frame);
     }
 
     fn draw_preview(&self, vct: VCTransform, _vcscale: f32, frame: &mut Frame) {
-        match self {
-            ChartElement::PlotTrace(trace) => {
-                let stroke = Stroke {
-                    width: 1.0,
-                    style: stroke::Style::Solid(Color::from_rgb(1.0, 1.0, 1.0)),
-                    line_cap: LineCap::Square,
-                    ..Stroke::default()
-                };
-                let mut path_builder = Builder::new();
-                for vsp in trace {
-                    path_builder.line_to(Point::from(vct.transform_point(*vsp)).into());
-                }
-                frame.stroke(&path_builder.build(), stroke.clone());
-            }
-        }
+        self.draw_color(vct, Color::from_rgb(1.0, 1.0, 1.0), frame);
     }
 }
 
 impl PlotElement for ChartElement {
     fn bounding_box(&self) -> VSBox {
-        match self {
-            ChartElement::PlotTrace(trace) => VSBox::from_points(trace),
-        }
+        VSBox::from_points(self.points())
     }
 }
 
@@ -131,6 +140,8 @@ pub enum Msg {
     None,
     /// new trace data
     Traces(Vec<Vec<VSPoint>>),
+    /// new poles and zeros (real part as x, imaginary part as y)
+    PoleZero(Vec<VSPoint>, Vec<VSPoint>),
     /// iced canvas event, along with cursor position inside canvas bounds
     Event(Event, VSPoint),
 }
@@ -298,6 +309,31 @@ impl viewport::Content<Msg> for Plot<ChartElement> {
                     self.content.insert(ChartElement::PlotTrace(trace));
                 }
 
+                clear_passive = true;
+            }
+            Msg::PoleZero(poles, zeros) => {
+                self.selected.clear();
+                self.tentatives.clear();
+                self.content.clear();
+
+                // axes spanning every point, the imaginary axis bounding the stable left half plane
+                let span = poles
+                    .iter()
+                    .chain(&zeros)
+                    .map(|p| p.x.abs().max(p.y.abs()))
+                    .fold(0.0, f32::max);
+                let span = if span > 0.0 { span * 1.2 } else { 1.0 };
+                self.content.insert(ChartElement::PlotTrace(vec![
+                    VSPoint::new(-span, 0.0),
+                    VSPoint::new(span, 0.0),
+                ]));
+                self.content.insert(ChartElement::PlotTrace(vec![
+                    VSPoint::new(0.0, -span),
+                    VSPoint::new(0.0, span),
+                ]));
+                self.content.insert(ChartElement::Poles(poles));
+                self.content.insert(ChartElement::Zeros(zeros));
+
                 clear_passive = true;
             }
         }
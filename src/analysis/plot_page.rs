@@ -8,19 +8,181 @@ use crate::analysis::{plot, viewport};
 use crate::transforms::VSPoint;
 
 use crate::IcedStruct;
-use iced::widget::row;
-use iced::Element;
+use iced::widget::{button, column, row, scrollable, text, text_input};
+use iced::{Element, Length};
+use num::Complex;
+use paprika::{read_rawfile, SimData, SimPlot};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum PlotPageMsg {
     ViewportEvt(viewport::CompositeMsg<plot::Msg>),
+    /// events of the lower viewport of bode and noise plots
+    LowerViewportEvt(viewport::CompositeMsg<plot::Msg>),
     Traces(Vec<Vec<VSPoint>>),
+    /// magnitude (dB) and phase (degrees) traces against log10 of frequency
+    Bode(Vec<Vec<VSPoint>>, Vec<Vec<VSPoint>>),
+    /// log10 of output and input-referred noise density traces against log10 of frequency, and integrated noise summary
+    Noise(Vec<Vec<VSPoint>>, Vec<Vec<VSPoint>>, String),
+    /// poles and zeros of a pole-zero analysis
+    PoleZero(Vec<Complex<f64>>, Vec<Complex<f64>>),
+    /// rawfile path editor text changed
+    PathChanged(String),
+    /// open the rawfile at the entered path
+    Open,
+}
+
+/// returns the real part of every vector of plot against its scale, skipping vectors without scale.
+/// If the scale is swept repeatedly, e.g. dc with a nested source, every sweep_points points begin another trace
+pub fn plot_traces(plot: &SimPlot, sweep_points: Option<usize>) -> Vec<Vec<VSPoint>> {
+    let mut traces = vec![];
+    for v in &plot.vecs {
+        let x = match plot.scale_of(v) {
+            Some(scale) => scale.data.real(),
+            None => continue,
+        };
+        let points: Vec<VSPoint> = v
+            .data
+            .real()
+            .into_iter()
+            .zip(x)
+            .map(|(y, x)| VSPoint::new(x as f32, y as f32))
+            .collect();
+        match sweep_points {
+            Some(n) if n > 0 => traces.extend(points.chunks(n).map(|c| c.to_vec())),
+            _ => traces.push(points),
+        }
+    }
+    traces
+}
+
+/// returns the points of each sweep of the scale of plot if it is swept repeatedly, i.e. returns to its first value
+pub fn sweep_points(plot: &SimPlot) -> Option<usize> {
+    let x = plot.vecs.iter().find_map(|v| plot.scale_of(v))?.data.real();
+    let first = *x.first()?;
+    x.iter().skip(1).position(|&xi| xi == first).map(|i| i + 1)
+}
+
+/// returns the magnitude (dB) and the unwrapped phase (degrees) of every complex vector of plot against log10 of its scale,
+/// skipping vectors without scale and points at or below 0 Hz
+pub fn bode_traces(plot: &SimPlot) -> (Vec<Vec<VSPoint>>, Vec<Vec<VSPoint>>) {
+    plot.vecs
+        .iter()
+        .filter_map(|v| {
+            let data = match &v.data {
+                SimData::Complex(data) => data,
+                SimData::Real(_) => return None,
+            };
+            let f = plot.scale_of(v)?.data.real();
+            let points = f.iter().zip(data).filter(|(f, _)| **f > 0.0);
+            let magnitude = points
+                .clone()
+                .map(|(f, c)| VSPoint::new(f.log10() as f32, (20.0 * c.norm().log10()) as f32))
+                .filter(|p| p.y.is_finite())
+                .collect();
+            // unwrap the phase along the sweep, rather than wrapping it into (-180, 180]
+            let phase = points
+                .scan(None, |prev: &mut Option<f64>, (f, c)| {
+                    let mut deg = c.arg().to_degrees();
+                    if let Some(prev) = *prev {
+                        deg -= 360.0 * ((deg - prev) / 360.0).round();
+                    }
+                    *prev = Some(deg);
+                    Some(VSPoint::new(f.log10() as f32, deg as f32))
+                })
+                .collect();
+            Some((magnitude, phase))
+        })
+        .unzip()
+}
+
+/// returns log10 of the output and of the input-referred noise density against log10 of frequency,
+/// from the spectrum plot of a noise analysis
+pub fn noise_traces(spectrum: &SimPlot) -> (Vec<Vec<VSPoint>>, Vec<Vec<VSPoint>>) {
+    let trace = |name: &str| -> Vec<Vec<VSPoint>> {
+        let v = match spectrum.get(name) {
+            Some(v) => v,
+            None => return vec![],
+        };
+        let f = match spectrum.scale_of(v) {
+            Some(f) => f.data.real(),
+            None => return vec![],
+        };
+        vec![f
+            .iter()
+            .zip(v.data.real())
+            .filter(|(f, d)| **f > 0.0 && *d > 0.0)
+            .map(|(f, d)| VSPoint::new(f.log10() as f32, d.log10() as f32))
+            .collect()]
+    };
+    (trace("onoise_spectrum"), trace("inoise_spectrum"))
+}
+
+/// returns the total output and input-referred noise of the integrated noise plot of a noise analysis,
+/// e.g. `onoise_total: 1.234e-5; inoise_total: 2.345e-6`
+pub fn noise_summary(integrated: &SimPlot) -> String {
+    ["onoise_total", "inoise_total"]
+        .iter()
+        .filter_map(|name| {
+            let total = *integrated.get(name)?.data.real().first()?;
+            Some(format!("{}: {:.3e}", name, total))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// returns the poles and the zeros of the plot of a pole-zero analysis, from its `pole(n)` and `zero(n)` vectors
+pub fn pz_values(plot: &SimPlot) -> (Vec<Complex<f64>>, Vec<Complex<f64>>) {
+    let values = |prefix: &str| {
+        plot.vecs
+            .iter()
+            .filter(|v| v.name.starts_with(prefix))
+            .flat_map(|v| match &v.data {
+                SimData::Complex(data) => data.clone(),
+                SimData::Real(data) => data.iter().map(|re| Complex::new(*re, 0.0)).collect(),
+            })
+            .collect()
+    };
+    (values("pole"), values("zero"))
+}
+
+/// returns true if plot has the results of a pole-zero analysis
+pub fn is_pz(plot: &SimPlot) -> bool {
+    plot.vecs
+        .iter()
+        .any(|v| v.name.starts_with("pole(") || v.name.starts_with("zero("))
+}
+
+/// kind of chart displayed, which sets the axes
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Chart {
+    /// traces against their scale, e.g. time
+    #[default]
+    Linear,
+    /// magnitude (dB) above phase (degrees), against log10 of frequency
+    Bode,
+    /// log10 of output above log10 of input-referred noise density, against log10 of frequency
+    Noise,
+    /// poles and zeros on the s-plane, listed next to it
+    PoleZero,
 }
 
 /// schematic
 pub struct PlotPage {
     /// viewport
     viewport: viewport::Viewport<Plot<ChartElement>, plot::Msg>,
+    /// viewport displayed below viewport, for the phase of bode plots and the input-referred noise of noise plots
+    lower_viewport: viewport::Viewport<Plot<ChartElement>, plot::Msg>,
+    /// kind of chart displayed
+    chart: Chart,
+
+    /// poles and zeros listed next to the s-plane, e.g. `pole: -1.000e3 +0.000e0j`
+    pz_list: Vec<String>,
+
+    /// rawfile path editor text
+    path_input: String,
+    /// status message, e.g. result of the last file operation, displayed in the infobar
+    status: String,
 }
 impl Default for PlotPage {
     fn default() -> Self {
@@ -29,6 +191,11 @@ impl Default for PlotPage {
             .then_scale(10.0, 10.0);
         PlotPage {
             viewport: viewport::Viewport::new(1.0, f32::EPSILON, f32::MAX, vct),
+            lower_viewport: viewport::Viewport::new(1.0, f32::EPSILON, f32::MAX, vct),
+            chart: Chart::Linear,
+            pz_list: vec![],
+            path_input: String::from("rawfile.raw"),
+            status: String::new(),
         }
     }
 }
@@ -39,26 +206,154 @@ impl IcedStruct<PlotPageMsg> for PlotPage {
             PlotPageMsg::ViewportEvt(msgs) => {
                 self.viewport.update(msgs);
             }
+            PlotPageMsg::LowerViewportEvt(msgs) => {
+                self.lower_viewport.update(msgs);
+            }
             PlotPageMsg::Traces(traces) => {
+                self.chart = Chart::Linear;
                 let content_msg = PlotMsg::Traces(traces);
                 self.viewport.content.update(content_msg);
             }
+            PlotPageMsg::Bode(magnitude, phase) => {
+                self.chart = Chart::Bode;
+                self.viewport.content.update(PlotMsg::Traces(magnitude));
+                self.lower_viewport.content.update(PlotMsg::Traces(phase));
+            }
+            PlotPageMsg::Noise(output, input, summary) => {
+                self.chart = Chart::Noise;
+                self.viewport.content.update(PlotMsg::Traces(output));
+                self.lower_viewport.content.update(PlotMsg::Traces(input));
+                self.status = summary;
+            }
+            PlotPageMsg::PoleZero(poles, zeros) => {
+                self.chart = Chart::PoleZero;
+                let vsp = |c: &Complex<f64>| VSPoint::new(c.re as f32, c.im as f32);
+                self.viewport.content.update(PlotMsg::PoleZero(
+                    poles.iter().map(vsp).collect(),
+                    zeros.iter().map(vsp).collect(),
+                ));
+                let fmt =
+                    |kind: &str, c: &Complex<f64>| format!("{}: {:.3e} {:+.3e}j", kind, c.re, c.im);
+                self.pz_list = poles
+                    .iter()
+                    .map(|c| {
+                        let s = fmt("pole", c);
+                        if c.re > 0.0 {
+                            s + " (rhp)"
+                        } else {
+                            s
+                        }
+                    })
+                    .chain(zeros.iter().map(|c| fmt("zero", c)))
+                    .collect();
+                let rhp = poles.iter().filter(|c| c.re > 0.0).count();
+                self.status = if rhp == 0 {
+                    String::from("stable: no right half plane poles")
+                } else {
+                    format!("unstable: {} right half plane pole(s)", rhp)
+                };
+            }
+            PlotPageMsg::PathChanged(s) => self.path_input = s,
+            PlotPageMsg::Open => {
+                let path = PathBuf::from(&self.path_input);
+                match read_rawfile(&path) {
+                    Ok(plots) => {
+                        if let Some(plot) = plots.iter().find(|p| is_pz(p)) {
+                            let (poles, zeros) = pz_values(plot);
+                            self.update(PlotPageMsg::PoleZero(poles, zeros));
+                            return;
+                        }
+                        let has = |name: &str| plots.iter().find(|p| p.get(name).is_some());
+                        if let Some(spectrum) = has("onoise_spectrum") {
+                            let (output, input) = noise_traces(spectrum);
+                            let summary = has("onoise_total").map_or(String::new(), noise_summary);
+                            self.update(PlotPageMsg::Noise(output, input, summary));
+                            return;
+                        }
+                        // the first plot swept along a scale, e.g. not an operating point
+                        match plots
+                            .iter()
+                            .find(|p| p.vecs.iter().any(|v| v.scale.is_some() && v.data.len() > 1))
+                        {
+                            Some(plot) => {
+                                self.status = format!("opened {}: {}", path.display(), plot.name);
+                                // complex results, e.g. ac analysis
+                                if plot
+                                    .vecs
+                                    .iter()
+                                    .any(|v| matches!(v.data, SimData::Complex(_)))
+                                {
+                                    let (magnitude, phase) = bode_traces(plot);
+                                    self.update(PlotPageMsg::Bode(magnitude, phase));
+                                } else {
+                                    self.update(PlotPageMsg::Traces(plot_traces(
+                                        plot,
+                                        sweep_points(plot),
+                                    )));
+                                }
+                            }
+                            None => self.status = format!("{}: no plot to display", path.display()),
+                        }
+                    }
+                    Err(e) => self.status = format!("{}: {}", path.display(), e),
+                }
+            }
         }
     }
 
     fn view(&self) -> Element<PlotPageMsg> {
-        let str_ssp = format!(
-            "curpos: x: {:.2e}; y: {:.2e}",
-            self.viewport.curpos_vsp().x,
-            self.viewport.curpos_vsp().y
-        );
+        let str_ssp = match self.chart {
+            Chart::PoleZero => format!(
+                "curpos: re: {:.2e}; im: {:.2e}",
+                self.viewport.curpos_vsp().x,
+                self.viewport.curpos_vsp().y
+            ),
+            Chart::Linear => format!(
+                "curpos: x: {:.2e}; y: {:.2e}",
+                self.viewport.curpos_vsp().x,
+                self.viewport.curpos_vsp().y
+            ),
+            Chart::Bode => format!(
+                "curpos: f: {:.2e} Hz; mag: {:.2} dB; phase: {:.1} deg",
+                10f32.powf(self.viewport.curpos_vsp().x),
+                self.viewport.curpos_vsp().y,
+                self.lower_viewport.curpos_vsp().y,
+            ),
+            Chart::Noise => format!(
+                "curpos: f: {:.2e} Hz; output: {:.2e} V/√Hz; input-referred: {:.2e} V/√Hz",
+                10f32.powf(self.viewport.curpos_vsp().x),
+                10f32.powf(self.viewport.curpos_vsp().y),
+                10f32.powf(self.lower_viewport.curpos_vsp().y),
+            ),
+        };
         let str_xyscales = format!(
             "scale: x: {:.2e}; y: {:.2e}",
             self.viewport.vct().x_scale(),
             self.viewport.vct().y_scale(),
         );
 
-        let canvas = self.viewport.view().map(PlotPageMsg::ViewportEvt);
+        let canvas: Element<PlotPageMsg> = match self.chart {
+            Chart::Linear => self.viewport.view().map(PlotPageMsg::ViewportEvt),
+            Chart::Bode | Chart::Noise => column![
+                self.viewport.view().map(PlotPageMsg::ViewportEvt),
+                self.lower_viewport
+                    .view()
+                    .map(PlotPageMsg::LowerViewportEvt),
+            ]
+            .spacing(2)
+            .into(),
+            Chart::PoleZero => row![
+                self.viewport.view().map(PlotPageMsg::ViewportEvt),
+                scrollable(column(
+                    self.pz_list
+                        .iter()
+                        .map(|s| text(s).size(14).into())
+                        .collect::<Vec<_>>()
+                ))
+                .width(300),
+            ]
+            .into(),
+        };
         let infobar = row![
             iced::widget::text(str_ssp)
                 .size(16)
@@ -68,10 +363,23 @@ impl IcedStruct<PlotPageMsg> for PlotPage {
                 .size(16)
                 .height(16)
                 .vertical_alignment(iced::alignment::Vertical::Center),
+            iced::widget::text(&self.status)
+                .size(16)
+                .height(16)
+                .vertical_alignment(iced::alignment::Vertical::Center),
         ]
         .spacing(10);
+        let filebar = row![
+            text("rawfile: "),
+            text_input("", &self.path_input)
+                .width(Length::Fill)
+                .on_input(PlotPageMsg::PathChanged)
+                .on_submit(PlotPageMsg::Open),
+            button("open").on_press(PlotPageMsg::Open),
+        ]
+        .width(Length::Fill);
 
-        let schematic = iced::widget::column![canvas, infobar,];
+        let schematic = column![canvas, infobar, filebar];
 
         schematic.into()
     }
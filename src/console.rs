@@ -0,0 +1,173 @@
+//! Console GUI page
+//! displays simulator output, one log per simulation run, and sends commands entered by the user to the simulator
+
+use crate::simulator::OutputLine;
+use crate::IcedStruct;
+use iced::widget::{button, column, pick_list, row, scrollable, text, text_input};
+use iced::{theme, Color, Command, Element, Font, Length};
+
+/// number of logs kept, oldest are dropped
+const MAX_LOGS: usize = 20;
+/// number of lines kept per log, oldest are dropped
+const MAX_LINES: usize = 5000;
+
+#[derive(Debug, Clone)]
+pub enum ConsolePageMsg {
+    /// command input text changed
+    InputChanged(String),
+    /// send the entered command to the simulator
+    Submit,
+    /// display the log of the given name
+    LogSel(String),
+    /// clear the displayed log
+    Clear,
+    /// a simulation was started, subsequent output is logged under the given analysis
+    NewRun(String),
+    /// simulator output
+    Output(Vec<OutputLine>),
+}
+
+/// output of a simulation run, or of commands entered before the first run
+struct Log {
+    name: String,
+    lines: Vec<OutputLine>,
+}
+
+/// console
+pub struct ConsolePage {
+    /// logs, oldest first - output goes to the last
+    logs: Vec<Log>,
+    /// index of the displayed log
+    selected: usize,
+    /// number of runs logged, used to name logs
+    runs: usize,
+    /// command input text
+    input: String,
+    /// command entered by the user, to be sent to the simulator
+    pub command: Option<String>,
+}
+
+impl Default for ConsolePage {
+    fn default() -> Self {
+        ConsolePage {
+            logs: vec![Log {
+                name: String::from("console"),
+                lines: vec![],
+            }],
+            selected: 0,
+            runs: 0,
+            input: String::new(),
+            command: None,
+        }
+    }
+}
+
+impl ConsolePage {
+    /// scrollable holding the displayed log
+    fn scroll_id() -> scrollable::Id {
+        scrollable::Id::new("console")
+    }
+    /// scroll the displayed log to its end
+    pub fn scroll_to_end<T>() -> Command<T> {
+        scrollable::snap_to(Self::scroll_id(), scrollable::RelativeOffset::END)
+    }
+    /// append output to the current log
+    fn push(&mut self, output: Vec<OutputLine>) {
+        let lines = &mut self.logs.last_mut().unwrap().lines;
+        for line in output {
+            // keep only the latest of consecutive status lines, e.g. progress
+            if let (OutputLine::Stat(_), Some(OutputLine::Stat(_))) = (&line, lines.last()) {
+                lines.pop();
+            }
+            lines.push(line);
+        }
+        if lines.len() > MAX_LINES {
+            lines.drain(..lines.len() - MAX_LINES);
+        }
+    }
+}
+
+impl IcedStruct<ConsolePageMsg> for ConsolePage {
+    fn update(&mut self, msg: ConsolePageMsg) {
+        match msg {
+            ConsolePageMsg::InputChanged(s) => self.input = s,
+            ConsolePageMsg::Submit => {
+                let cmd = self.input.trim();
+                if !cmd.is_empty() {
+                    self.command = Some(cmd.to_string());
+                    self.input.clear();
+                }
+            }
+            ConsolePageMsg::LogSel(name) => {
+                if let Some(i) = self.logs.iter().position(|l| l.name == name) {
+                    self.selected = i;
+                }
+            }
+            ConsolePageMsg::Clear => self.logs[self.selected].lines.clear(),
+            ConsolePageMsg::NewRun(analysis) => {
+                self.runs += 1;
+                self.logs.push(Log {
+                    name: format!("run {}: {}", self.runs, analysis),
+                    lines: vec![],
+                });
+                if self.logs.len() > MAX_LOGS {
+                    self.logs.remove(0);
+                }
+                self.selected = self.logs.len() - 1;
+            }
+            ConsolePageMsg::Output(output) => self.push(output),
+        }
+    }
+
+    fn view(&self) -> Element<ConsolePageMsg> {
+        let log = &self.logs[self.selected];
+        let lines = log
+            .lines
+            .iter()
+            .map(|l| {
+                let (s, style) = match l {
+                    OutputLine::Command(s) => (
+                        format!("> {}", s),
+                        theme::Text::Color(Color::from_rgb(0.5, 0.5, 0.5)),
+                    ),
+                    OutputLine::Stdout(s) => (s.clone(), theme::Text::Default),
+                    OutputLine::Stderr(s) => (
+                        s.clone(),
+                        theme::Text::Color(Color::from_rgb(1.0, 0.3, 0.3)),
+                    ),
+                    OutputLine::Stat(s) => (
+                        s.clone(),
+                        theme::Text::Color(Color::from_rgb(0.3, 0.5, 1.0)),
+                    ),
+                };
+                text(s).size(14).font(Font::MONOSPACE).style(style).into()
+            })
+            .collect();
+        let output = scrollable(column(lines).width(Length::Fill))
+            .id(Self::scroll_id())
+            .width(Length::Fill)
+            .height(Length::Fill);
+        let logbar = row![
+            text("log: "),
+            pick_list(
+                self.logs.iter().map(|l| l.name.clone()).collect::<Vec<_>>(),
+                Some(log.name.clone()),
+                ConsolePageMsg::LogSel,
+            ),
+            button("clear").on_press(ConsolePageMsg::Clear),
+        ]
+        .spacing(10)
+        .width(Length::Fill);
+        let inputbar = row![
+            text("ngspice> "),
+            text_input("", &self.input)
+                .width(Length::Fill)
+                .on_input(ConsolePageMsg::InputChanged)
+                .on_submit(ConsolePageMsg::Submit),
+            button("send").on_press(ConsolePageMsg::Submit),
+        ]
+        .width(Length::Fill);
+
+        column![logbar, output, inputbar].into()
+    }
+}
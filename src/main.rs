@@ -4,15 +4,20 @@
 use std::fmt::Debug;
 
 mod analysis;
+mod console;
+mod preferences;
 mod schematic;
+mod simulator;
 mod transforms;
 
-use analysis::plot_page::{PlotPage, PlotPageMsg};
+use analysis::plot_page::PlotPage;
+use console::{ConsolePage, ConsolePageMsg};
 use enum_dispatch::enum_dispatch;
 use schematic::circuit::CircuitSchematicPage;
 use schematic::symbols::SymbolDesignerPage;
+use simulator::OutputLine;
 
-use iced::{executor, Application, Command, Element, Settings, Theme};
+use iced::{executor, Application, Command, Element, Settings, Subscription, Theme};
 
 use iced_aw::{TabLabel, Tabs};
 
@@ -41,6 +46,8 @@ pub struct Circe {
     circuit_schematic: CircuitSchematicPage,
     /// dev use only - for drawing custom devices or new device graphics
     symbol_designer: SymbolDesignerPage,
+    /// simulator output and commands
+    console: ConsolePage,
 }
 
 #[derive(Debug, Clone)]
@@ -48,9 +55,25 @@ pub enum Msg {
     DesignerMsg(schematic::symbols::DevicePageMsg),
     SchematicMsg(schematic::circuit::CircuitPageMsg),
     PlotViewMsg(analysis::plot_page::PlotPageMsg),
+    ConsoleMsg(console::ConsolePageMsg),
     TabSel(usize),
 }
 
+impl Circe {
+    /// transfer simulator output from circuit_schematic to console, scrolling to the end if there is any
+    fn console_output(&mut self) -> Command<Msg> {
+        if let Some(card) = self.circuit_schematic.run_started.take() {
+            self.console.update(ConsolePageMsg::NewRun(card));
+        }
+        let output = self.circuit_schematic.take_output();
+        if output.is_empty() {
+            return Command::none();
+        }
+        self.console.update(ConsolePageMsg::Output(output));
+        ConsolePage::scroll_to_end()
+    }
+}
+
 impl Application for Circe {
     type Executor = executor::Default;
     type Message = Msg;
@@ -63,6 +86,7 @@ impl Application for Circe {
                 circuit_schematic: CircuitSchematicPage::default(),
                 symbol_designer: SymbolDesignerPage::default(),
                 plot_view: PlotPage::default(),
+                console: ConsolePage::default(),
                 active_tab: 1,
             },
             Command::none(),
@@ -79,8 +103,7 @@ impl Application for Circe {
                 self.active_tab = i;
 
                 // transfer simulation results from circuit_schematic to plot
-                if let Some(traces) = self.circuit_schematic.traces.take() {
-                    let msg = PlotPageMsg::Traces(traces);
+                if let Some(msg) = self.circuit_schematic.plot.take() {
                     self.plot_view.update(msg);
                 }
             }
@@ -92,21 +115,44 @@ impl Application for Circe {
             }
             Msg::SchematicMsg(schematic_msg) => {
                 self.circuit_schematic.update(schematic_msg);
+                return self.console_output();
+            }
+            Msg::ConsoleMsg(console_msg) => {
+                self.console.update(console_msg);
+                if let Some(cmd) = self.console.command.take() {
+                    self.console
+                        .update(ConsolePageMsg::Output(vec![OutputLine::Command(
+                            cmd.clone(),
+                        )]));
+                    let result = self.circuit_schematic.command(&cmd);
+                    self.console_output();
+                    if let Err(e) = result {
+                        self.console
+                            .update(ConsolePageMsg::Output(vec![OutputLine::Stderr(e)]));
+                    }
+                    return ConsolePage::scroll_to_end();
+                }
             }
         }
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Msg> {
+        self.circuit_schematic.subscription().map(Msg::SchematicMsg)
+    }
+
     fn view(&self) -> Element<Msg> {
         let schematic = self.circuit_schematic.view().map(Msg::SchematicMsg);
         let plot = self.plot_view.view().map(Msg::PlotViewMsg);
         let devices = self.symbol_designer.view().map(Msg::DesignerMsg);
+        let console = self.console.view().map(Msg::ConsoleMsg);
 
         let tabs = Tabs::with_tabs(
             vec![
                 (0, TabLabel::Text("Graphs".to_string()), plot),
                 (1, TabLabel::Text("Schematic".to_string()), schematic),
                 (2, TabLabel::Text("Device Designer".to_string()), devices),
+                (3, TabLabel::Text("Console".to_string()), console),
             ],
             Msg::TabSel,
         );
@@ -0,0 +1,61 @@
+//! Preferences
+//! user preferences, read from a json file in the user's configuration directory
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// simulator backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulatorKind {
+    /// ngspice shared library, loaded in-process
+    #[default]
+    Shared,
+    /// ngspice executable, run in a separate process for each simulation
+    Subprocess,
+}
+
+/// user preferences
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// simulator backend - subprocess isolates circe from ngspice crashes, shared is faster
+    pub simulator: SimulatorKind,
+    /// path of the ngspice shared library, or of the directory containing it
+    pub ngspice_path: Option<PathBuf>,
+    /// path of the ngspice executable for the subprocess simulator, `ngspice` from the search path if none
+    pub ngspice_bin: Option<PathBuf>,
+    /// input path for files loaded by XSPICE code models
+    pub cm_input_path: Option<PathBuf>,
+    /// XSPICE code model libraries (.cm files) to load into ngspice at startup
+    pub codemodels: Vec<PathBuf>,
+}
+
+impl Preferences {
+    /// returns the path of the preferences file: `CIRCE_PREFERENCES` if set,
+    /// otherwise `circe/preferences.json` in the user's configuration directory
+    pub fn path() -> Option<PathBuf> {
+        if let Some(p) = std::env::var_os("CIRCE_PREFERENCES") {
+            return Some(PathBuf::from(p));
+        }
+        #[cfg(target_family = "windows")]
+        let config_dir = std::env::var_os("APPDATA").map(PathBuf::from);
+        #[cfg(not(target_family = "windows"))]
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+        config_dir.map(|d| d.join("circe").join("preferences.json"))
+    }
+
+    /// load preferences from the preferences file. Returns the defaults if there is no preferences file,
+    /// or an error message if it could not be read
+    pub fn load() -> Result<Self, String> {
+        let path = match Preferences::path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Preferences::default()),
+        };
+        let s = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        serde_json::from_str(&s).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
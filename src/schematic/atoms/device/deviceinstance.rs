@@ -73,6 +73,21 @@ impl Hash for Identifier {
     }
 }
 
+/// serializable description of a device instance, used for saving and loading schematics
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DeviceRecord {
+    /// id prefix of the device class - determines the device class on load
+    pub class: String,
+    /// identifier watermark
+    pub wm: usize,
+    /// user set identifier, if any
+    pub custom_id: Option<String>,
+    /// raw spice parameter (netlist line after port connections)
+    pub param: String,
+    /// device transform - position and orientation in schematic space
+    pub transform: SSTransform,
+}
+
 /// A device - e.g. a resistor, bjt, voltage source, ground
 #[derive(Debug, Clone)]
 pub struct Device {
@@ -114,6 +129,29 @@ impl Device {
             op: vec![],
         }
     }
+    /// returns the identifier watermark of the device
+    pub fn wm(&self) -> usize {
+        self.id.wm
+    }
+    /// returns the serializable description of this device
+    pub fn to_record(&self) -> DeviceRecord {
+        DeviceRecord {
+            class: self.class.id_prefix().to_string(),
+            wm: self.id.wm,
+            custom_id: self.id.custom.clone(),
+            param: self.class.param_summary(),
+            transform: self.transform,
+        }
+    }
+    /// recreates a device from its serializable description. Returns none if the device class is unknown
+    pub fn from_record(record: &DeviceRecord) -> Option<Self> {
+        let mut class = DeviceClass::from_id_prefix(&record.class)?;
+        class.set_raw_param(record.param.clone());
+        let mut device = Device::new_with_ord_class(record.wm, class);
+        device.id.custom = record.custom_id.clone();
+        device.transform(sst_to_vvt(record.transform));
+        Some(device)
+    }
     /// returns the schematic coordiantes of the devices ports in order
     pub fn ports_ssp(&self) -> Vec<SSPoint> {
         self.class
@@ -146,26 +184,32 @@ impl Device {
             .cast()
             .cast_unit();
     }
-    /// returns the device's spice netlist line
-    pub fn spice_line(&mut self, nets: &Nets) -> String {
+    /// returns the device's identifier as used in the netlist. E.g. V1, R0
+    pub fn ng_id(&self) -> String {
+        self.id.ng_id()
+    }
+    /// returns the device's spice netlist line, or the coordinates of all ports without a connected net
+    pub fn spice_line(&mut self, nets: &Nets) -> Result<String, Vec<SSPoint>> {
         self.connected_nets.clear();
         let mut sline = self.id.ng_id();
         sline.push(' ');
+        let mut unconnected = vec![];
         for p in self.class.graphics().ports() {
             let pt = self.transform.transform_point(p.offset);
-            let net_name;
-            if let Some(nn) = nets.net_name_at(pt) {
-                net_name = nn;
+            if let Some(net_name) = nets.net_name_at(pt) {
+                sline.push_str(&net_name);
+                sline.push(' ');
+                self.connected_nets.push(net_name);
             } else {
-                panic!("no net found at port location");
+                unconnected.push(pt);
             }
-            sline.push_str(&net_name);
-            sline.push(' ');
-            self.connected_nets.push(net_name);
+        }
+        if !unconnected.is_empty() {
+            return Err(unconnected);
         }
         sline.push_str(&self.class.param_summary());
         sline.push('\n');
-        sline
+        Ok(sline)
     }
     /// fill in the operating point for the device
     pub fn op(&mut self, pkvecvaluesall: &paprika::PkVecvaluesall) {
@@ -210,4 +210,19 @@ impl DeviceClass {
             DeviceClass::D(_) => d::ID_PREFIX,
         }
     }
+    /// returns a default device of the class denoted by id prefix, if any
+    pub fn from_id_prefix(id_prefix: &str) -> Option<Self> {
+        match id_prefix {
+            pmos::ID_PREFIX => Some(DeviceClass::Pm(pmos::M::default())),
+            nmos::ID_PREFIX => Some(DeviceClass::Nm(nmos::M::default())),
+            gnd::ID_PREFIX => Some(DeviceClass::Gnd(gnd::Gnd::default())),
+            r::ID_PREFIX => Some(DeviceClass::R(r::R::default())),
+            l::ID_PREFIX => Some(DeviceClass::L(l::L::default())),
+            c::ID_PREFIX => Some(DeviceClass::C(c::C::default())),
+            v::ID_PREFIX => Some(DeviceClass::V(v::V::default())),
+            i::ID_PREFIX => Some(DeviceClass::I(i::I::default())),
+            d::ID_PREFIX => Some(DeviceClass::D(d::D::default())),
+            _ => None,
+        }
+    }
 }
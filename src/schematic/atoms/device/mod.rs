@@ -6,7 +6,7 @@ pub mod deviceinstance;
 pub mod devicetype;
 mod params;
 
-use deviceinstance::Device;
+use deviceinstance::{Device, DeviceRecord};
 
 use by_address::ByAddress;
 
@@ -34,6 +34,10 @@ impl RcRDevice {
     pub fn new_with_ord_class(wm: usize, class: DeviceClass) -> Self {
         RcRDevice(Rc::new(RefCell::new(Device::new_with_ord_class(wm, class))))
     }
+    /// creates a new device from its serializable description. Returns none if the device class is unknown
+    pub fn from_record(record: &DeviceRecord) -> Option<Self> {
+        Device::from_record(record).map(|d| RcRDevice(Rc::new(RefCell::new(d))))
+    }
 }
 
 impl SchematicAtom for RcRDevice {
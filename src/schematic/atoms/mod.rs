@@ -15,10 +15,12 @@ pub use cirarc::RcRCirArc;
 pub use lineseg::LineSeg;
 pub use lineseg::RcRLineSeg;
 
+pub use device::deviceinstance::DeviceRecord;
 pub use device::devicetype;
 pub use device::devicetype::DeviceClass;
 pub use device::RcRDevice;
 
+pub use net_label::LabelRecord;
 pub use net_label::RcRLabel;
 
 pub use nets::{NetEdge, NetVertex};
@@ -27,6 +27,15 @@ use iced::{
 
 use by_address::ByAddress;
 
+/// serializable description of a net label, used for saving and loading schematics
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct LabelRecord {
+    /// net label
+    pub name: String,
+    /// label transform - position and orientation in schematic space
+    pub transform: SSTransform,
+}
+
 /// net label, which can be user set
 #[derive(Debug, Clone)]
 pub struct NetLabel {
@@ -79,6 +88,24 @@ impl NetLabel {
     pub fn pos(&self) -> SSPoint {
         self.transform.transform_point(SSPoint::origin())
     }
+
+    /// returns the serializable description of this label
+    pub fn to_record(&self) -> LabelRecord {
+        LabelRecord {
+            name: self.name.clone(),
+            transform: self.transform,
+        }
+    }
+
+    /// recreates a label from its serializable description
+    pub fn from_record(record: &LabelRecord) -> Self {
+        let mut label = NetLabel {
+            name: record.name.clone(),
+            ..Default::default()
+        };
+        label.transform(sst_to_vvt(record.transform));
+        label
+    }
 }
 
 impl Drawable for NetLabel {
@@ -178,6 +205,12 @@ impl Interactive for NetLabel {
 /// newtype wrapper for `Rc<RefCell<NetLabel>>`. Hashes by memory address.
 #[derive(Debug, Default, Clone)]
 pub struct RcRLabel(pub Rc<RefCell<NetLabel>>);
+impl RcRLabel {
+    /// creates a new label from its serializable description
+    pub fn from_record(record: &LabelRecord) -> Self {
+        RcRLabel(Rc::new(RefCell::new(NetLabel::from_record(record))))
+    }
+}
 impl PartialEq for RcRLabel {
     fn eq(&self, other: &Self) -> bool {
         ByAddress(self.0.clone()) == ByAddress(other.0.clone())
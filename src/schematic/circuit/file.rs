@@ -0,0 +1,253 @@
+//! Circuit schematic file
+//! versioned on-disk format for saving and loading circuit schematics
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::schematic::atoms::{DeviceRecord, LabelRecord, NetEdge, NetVertex, RcRDevice, RcRLabel};
+use crate::schematic::models::NgModels;
+use crate::transforms::SSPoint;
+
+use super::Circuit;
+
+/// version of the schematic file format written by this build
+pub const FORMAT_VERSION: u32 = 1;
+
+/// errors which may occur while saving or loading a schematic
+#[derive(Debug)]
+pub enum FileError {
+    /// file could not be read or written
+    Io(std::io::Error),
+    /// file content is not a valid schematic
+    Parse(serde_json::Error),
+    /// file was written by an unsupported version of the format
+    Version(u32),
+    /// file contains a device of unknown class
+    UnknownDevice(String),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "file error: {}", e),
+            FileError::Parse(e) => write!(f, "invalid schematic file: {}", e),
+            FileError::Version(v) => write!(
+                f,
+                "unsupported schematic file version {} (expected {})",
+                v, FORMAT_VERSION
+            ),
+            FileError::UnknownDevice(c) => write!(f, "unknown device class: {}", c),
+        }
+    }
+}
+
+impl From<std::io::Error> for FileError {
+    fn from(value: std::io::Error) -> Self {
+        FileError::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for FileError {
+    fn from(value: serde_json::Error) -> Self {
+        FileError::Parse(value)
+    }
+}
+
+/// on-disk representation of a circuit schematic
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CircuitFile {
+    /// format version, checked on load
+    pub version: u32,
+    /// device instances
+    pub devices: Vec<DeviceRecord>,
+    /// wire segments as (source, destination) pairs
+    pub wires: Vec<(SSPoint, SSPoint)>,
+    /// net labels
+    pub labels: Vec<LabelRecord>,
+    /// ngspice model definitions
+    pub models: NgModels,
+}
+
+impl Circuit {
+    /// returns the on-disk representation of self
+    pub fn to_file(&self) -> CircuitFile {
+        let mut devices: Vec<_> = self
+            .devices_layer()
+            .get_set()
+            .iter()
+            .map(|d| d.0.borrow().to_record())
+            .collect();
+        // sort for stable output between saves
+        devices.sort_by(|a, b| (&a.class, a.wm).cmp(&(&b.class, b.wm)));
+
+        let mut wires: Vec<_> = self
+            .nets_layer()
+            .graph
+            .all_edges()
+            .filter(|e| e.2.src != e.2.dst) // skip edges added to connect overlapping ports
+            .map(|e| (e.2.src, e.2.dst))
+            .collect();
+        wires.sort_by_key(|(src, dst)| (src.x, src.y, dst.x, dst.y));
+
+        let labels = self
+            .labels_layer()
+            .get_set()
+            .iter()
+            .map(|l| l.0.borrow().to_record())
+            .collect();
+
+        CircuitFile {
+            version: FORMAT_VERSION,
+            devices,
+            wires,
+            labels,
+            models: self.device_models.clone(),
+        }
+    }
+
+    /// creates a circuit from its on-disk representation
+    pub fn from_file(file: &CircuitFile) -> Result<Self, FileError> {
+        if file.version != FORMAT_VERSION {
+            return Err(FileError::Version(file.version));
+        }
+        let mut circuit = Circuit {
+            device_models: file.models.clone(),
+            ..Default::default()
+        };
+        for record in &file.devices {
+            let d = RcRDevice::from_record(record)
+                .ok_or_else(|| FileError::UnknownDevice(record.class.clone()))?;
+            circuit.devices_layer_mut().insert_keep_id(d);
+        }
+        for (src, dst) in &file.wires {
+            circuit.nets_layer_mut().graph.add_edge(
+                NetVertex(*src),
+                NetVertex(*dst),
+                NetEdge::new_from_pts(*src, *dst),
+            );
+        }
+        for record in &file.labels {
+            circuit
+                .labels_layer_mut()
+                .insert(RcRLabel::from_record(record));
+        }
+        circuit.prune();
+        Ok(circuit)
+    }
+
+    /// save the circuit to path
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        let s = serde_json::to_string_pretty(&self.to_file())?;
+        std::fs::write(path, s)?;
+        Ok(())
+    }
+
+    /// load a circuit from path
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        let s = std::fs::read_to_string(path)?;
+        let file: CircuitFile = serde_json::from_str(&s)?;
+        Circuit::from_file(&file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schematic::circuit::NetlistError;
+    use crate::transforms::SSTransform;
+
+    /// circuit file with devices, a wire, a label and a model
+    fn circuit_file() -> CircuitFile {
+        let device = |class: &str, wm: usize, param: &str, x: i16| DeviceRecord {
+            class: String::from(class),
+            wm,
+            custom_id: None,
+            param: String::from(param),
+            transform: SSTransform::translation(x, 0),
+        };
+        CircuitFile {
+            version: FORMAT_VERSION,
+            devices: vec![
+                device("R", 1, "1k", 0),
+                device("R", 5, "2k", 10),
+                device("V", 2, "DC 1", 20),
+            ],
+            wires: vec![(SSPoint::new(30, 30), SSPoint::new(40, 30))],
+            labels: vec![LabelRecord {
+                name: String::from("out"),
+                transform: SSTransform::translation(30, 30),
+            }],
+            models: serde_json::from_str(r#"{"models":[{"name":"DX","definition":"D is=1e-14"}]}"#)
+                .unwrap(),
+        }
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let circuit = Circuit::from_file(&circuit_file()).unwrap();
+        let path = std::env::temp_dir().join("circe_save_load_round_trip.json");
+        circuit.save(&path).unwrap();
+        let mut loaded = Circuit::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let saved = serde_json::to_value(circuit.to_file()).unwrap();
+        assert_eq!(serde_json::to_value(loaded.to_file()).unwrap(), saved);
+        assert_eq!(saved["devices"].as_array().unwrap().len(), 3);
+        assert_eq!(saved["wires"].as_array().unwrap().len(), 1);
+        assert_eq!(saved["labels"][0]["name"], "out");
+        assert_eq!(saved["models"]["models"][0]["name"], "DX");
+
+        // new devices are numbered after the highest loaded ID of their class
+        let r = loaded.devices_layer_mut().new_res();
+        loaded.devices_layer_mut().insert(r.clone());
+        assert_eq!(r.0.borrow().ng_id(), "R6");
+    }
+
+    #[test]
+    fn netlist_devices_and_models() {
+        let circuit = Circuit::from_file(&circuit_file()).unwrap();
+        let netlist = circuit.netlist().unwrap();
+        let lines: Vec<&str> = netlist.lines().collect();
+        assert_eq!(lines[0], "Netlist Created by Circe");
+        assert_eq!(lines[1], ".model DX D is=1e-14");
+        // device lines are sorted, each port on a net of its own
+        assert_eq!(lines.iter().filter(|l| !l.is_empty()).count(), 5);
+        for (line, (id, param)) in
+            lines[2..5]
+                .iter()
+                .zip([("R1", "1k"), ("R5", "2k"), ("V2", "DC 1")])
+        {
+            let words: Vec<&str> = line.splitn(4, ' ').collect();
+            assert_eq!(words[0], id);
+            assert_eq!(words[3], param);
+            assert_ne!(words[1], words[2]);
+        }
+    }
+
+    #[test]
+    fn netlist_unconnected_port() {
+        let mut circuit = Circuit::from_file(&circuit_file()).unwrap();
+        // placed without pruning, so its ports are on no net
+        let r = circuit.devices_layer_mut().new_res();
+        r.0.borrow_mut().set_position(SSPoint::new(50, 0));
+        circuit.devices_layer_mut().insert(r.clone());
+        let ports = r.0.borrow().ports_ssp();
+
+        let errors = circuit.netlist().unwrap_err();
+        assert_eq!(errors.len(), ports.len());
+        for (error, port) in errors.iter().zip(ports) {
+            assert_eq!(
+                *error,
+                NetlistError::UnconnectedPort {
+                    device: String::from("R6"),
+                    port,
+                }
+            );
+        }
+
+        circuit.prune();
+        assert!(circuit.netlist().is_ok());
+    }
+}
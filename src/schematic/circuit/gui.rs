@@ -3,74 +3,96 @@
 //! waiting on multiple windows support for new device instance menu
 
 use crate::schematic;
+use crate::schematic::circuit::table::{ResultTable, TableMsg};
 use crate::schematic::circuit::{Circuit, CircuitAtom, Msg};
 use crate::schematic::viewport::CompositeMsg;
 use crate::schematic::viewport::VCTransformLockedAspect;
-use crate::transforms::VSPoint;
 
+use crate::analysis::plot_page::{
+    bode_traces, noise_summary, noise_traces, plot_traces, pz_values, PlotPageMsg,
+};
+use crate::preferences::Preferences;
 use crate::schematic::viewport;
 use crate::schematic::viewport::Viewport;
 use crate::schematic::Schematic;
+use crate::simulator::{self, diagnostics, plot_values, OutputLine, Simulator};
 use crate::IcedStruct;
 use iced::keyboard::Modifiers;
 use iced::widget::canvas::Event;
-use iced::widget::{row, text, text_input};
-use iced::{Element, Length};
-use std::sync::{Arc, Mutex};
+use iced::widget::{button, pick_list, row, text, text_input};
+use iced::{Element, Length, Subscription};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use colored::Colorize;
 use paprika::*;
 
-/// Spice Manager to facillitate interaction with NgSpice
-#[derive(Debug, Default)]
-struct SpManager {
-    vecvals: Mutex<Vec<PkVecvaluesall>>,
-    vecinfo: Option<PkVecinfoall>,
-}
-
-impl SpManager {
-    fn new() -> Self {
-        SpManager::default()
-    }
-}
-
-#[allow(unused_variables)]
-impl paprika::PkSpiceManager for SpManager {
-    fn cb_send_char(&mut self, msg: String, id: i32) {
-        let opt = msg.split_once(' ');
-        let (token, msgs) = match opt {
-            Some(tup) => (tup.0, tup.1),
-            None => (msg.as_str(), msg.as_str()),
-        };
-        let msgc = match token {
-            "stdout" => msgs.green(),
-            "stderr" => msgs.red(),
-            _ => msg.magenta().strikethrough(),
-        };
-        println!("{}", msgc);
-    }
-    fn cb_send_stat(&mut self, msg: String, id: i32) {
-        println!("{}", msg.blue());
-    }
-    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
-    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {
-        self.vecinfo = Some(pkvecinfoall);
-    }
-    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {
-        // this is called every simulation step when running tran
-        self.vecvals.try_lock().unwrap().push(pkvecvaluesall);
-    }
-    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {}
-}
-
 #[derive(Debug, Clone)]
 pub enum CircuitPageMsg {
     ViewportEvt(viewport::CompositeMsg<schematic::Msg<Msg, CircuitAtom>>),
     ParamChanged(String),
     ParamSubmit,
-    HzChanged(String),
+    /// dc sweep source selected, index 0 is the swept source and 1 the nested source
+    DcSrcSel(usize, String),
+    DcStartChanged(usize, String),
+    DcStopChanged(usize, String),
+    DcStepChanged(usize, String),
+    /// small-signal output net selected
+    OutNetSel(String),
+    RefNetSel(String),
+    InSrcSel(String),
+    /// pole-zero input net selected
+    PzInSel(String),
+    PzRefSel(String),
+    AcScaleSel(SweepScale),
+    AcPointsChanged(String),
+    AcStartChanged(String),
+    AcStopChanged(String),
     StepChanged(String),
     TranChanged(String),
+    StopAtChanged(String),
+    PathChanged(String),
+    Open,
+    Save,
+    SaveAs,
+    /// poll the simulation running in the background
+    SimPoll,
+    /// halt the simulation running in the background
+    SimStop,
+    /// continue the simulation paused at a breakpoint
+    SimContinue,
+    TableEvt(TableMsg),
+}
+
+/// simulations which may be run from the schematic
+#[derive(Debug, Clone, Copy)]
+enum Sim {
+    DcOp,
+    Dc,
+    Ac,
+    Noise,
+    Tf,
+    /// ac sensitivity over the frequency sweep if true, otherwise dc
+    Sens(bool),
+    Pz,
+    Tran,
+}
+
+/// source selection entry for no source
+const NO_SOURCE: &str = "none";
+/// name of the ground net
+const GROUND: &str = "0";
+
+/// dc sweep setup as entered
+struct DcSweepInput {
+    /// swept source, none if not selected
+    src: Option<String>,
+    /// start value (volts or amperes)
+    start: String,
+    /// stop value (volts or amperes)
+    stop: String,
+    /// increment (volts or amperes)
+    step: String,
 }
 
 /// schematic
@@ -80,80 +102,535 @@ pub struct CircuitSchematicPage {
 
     /// tentative net name, used only for display in the infobar
     net_name: Option<String>,
+    /// status message, e.g. result of the last file operation, displayed in the infobar
+    status: String,
 
-    /// spice manager
-    spmanager: Arc<SpManager>,
-    /// ngspice library
-    lib: PkSpice<SpManager>,
-    /// traces from certain simulations e.g. transient
-    pub traces: Option<Vec<Vec<VSPoint>>>,
+    /// path of the schematic file currently open, if any
+    file_path: Option<PathBuf>,
+    /// file path editor text
+    path_input: String,
+
+    /// simulator, none if it could not be created - simulation is disabled
+    simulator: Option<Box<dyn Simulator>>,
+    /// results of certain simulations e.g. transient, for the plot page
+    pub plot: Option<PlotPageMsg>,
+    /// analysis card of the simulation started since the console last took it, to begin a new log
+    pub run_started: Option<String>,
+    /// simulator output not yet taken by the console
+    output: Vec<OutputLine>,
+    /// simulator output since the start of the last simulation, searched for problems once it finishes
+    run_output: Vec<OutputLine>,
+    /// scalar results of the last tf or sens analysis, displayed next to the canvas
+    table: Option<ResultTable>,
+    /// analysis card of the last simulation started
+    card: String,
+    /// points of each sweep of the scale of the last simulation started, if it sweeps the scale repeatedly
+    sweep_points: Option<usize>,
+    /// simulation running in the background, if any
+    sim: Option<Sim>,
+    /// time (seconds) of the breakpoint set for the running transient simulation, if any
+    breakpoint: Option<f64>,
+    /// time (seconds) at which the running transient simulation is paused, if any
+    paused_at: Option<f64>,
 
     /// active device - some if only 1 device selected, otherwise is none
     active_element: Option<CircuitAtom>,
     /// parameter editor text
     param: String,
-    /// ac simulation frequency (hertz)
-    ac_hz: String,
+    /// dc sweep setup, index 0 is the swept source and 1 the nested source, which sweeps every curve of the first
+    dc: [DcSweepInput; 2],
+    /// small-signal (noise, tf, sens, pz) output net, none if not selected
+    out_net: Option<String>,
+    /// small-signal output reference net
+    ref_net: String,
+    /// small-signal (noise, tf) input source, none if not selected
+    in_src: Option<String>,
+    /// pole-zero input net, none if not selected
+    pz_in: Option<String>,
+    /// pole-zero input reference net
+    pz_ref: String,
+    /// ac, noise and ac sens sweep point spacing
+    ac_scale: SweepScale,
+    /// ac, noise and ac sens sweep number of points, per decade/octave or in total depending on ac_scale
+    ac_points: String,
+    /// ac, noise and ac sens sweep start frequency (hertz)
+    ac_start: String,
+    /// ac, noise and ac sens sweep stop frequency (hertz)
+    ac_stop: String,
     /// tran simulation step size (seconds)
     tran_step: String,
     /// tran simulation end time (seconds)
     tran_end: String,
+    /// tran simulation breakpoint (seconds), no breakpoint if empty
+    stop_at: String,
 }
 impl Default for CircuitSchematicPage {
     fn default() -> Self {
-        let spmanager = Arc::new(SpManager::new());
-        let mut lib;
-        #[cfg(target_family = "windows")]
-        {
-            lib = PkSpice::<SpManager>::new(std::ffi::OsStr::new("ngspice.dll")).unwrap();
-        }
-        #[cfg(target_os = "macos")]
-        {
-            // retrieve libngspice.dylib from the following possible directories
-            let ret = std::process::Command::new("find")
-                .args(&["/usr/lib", "/usr/local/lib"])
-                .arg("-name")
-                .arg("*libngspice.dylib")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice.dylib. Make sure it is installed.");
-                    std::process::exit(1);
-                });
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
-        }
-        #[cfg(target_os = "linux")]
-        {
-            // dynamically retrieves libngspice from system
-            let ret = std::process::Command::new("sh")
-                .arg("-c")
-                .arg("ldconfig -p | grep ngspice | awk '/.*libngspice.so$/{print $4}'")
-                .stdout(std::process::Stdio::piped())
-                .output()
-                .unwrap_or_else(|_| {
-                    eprintln!("Error: Could not find libngspice. Make sure it is installed.");
-                    std::process::exit(1);
-                });
-
-            let path = String::from_utf8(ret.stdout).unwrap();
-            lib = PkSpice::<SpManager>::new(&std::ffi::OsString::from(path.trim())).unwrap();
-        }
-        lib.init(Some(spmanager.clone()));
+        let (prefs, mut status) = match Preferences::load() {
+            Ok(prefs) => (prefs, String::new()),
+            Err(e) => (
+                Preferences::default(),
+                format!("could not load preferences: {}", e),
+            ),
+        };
+        let simulator = match simulator::from_preferences(&prefs) {
+            Ok(simulator) => Some(simulator),
+            Err(e) => {
+                status = e;
+                None
+            }
+        };
         let vct = VCTransformLockedAspect::identity()
             .pre_flip_y()
             .then_scale(10.0);
         CircuitSchematicPage {
             viewport: viewport::Viewport::new(1.0, 100.0, vct),
             net_name: Default::default(),
+            status,
+            file_path: None,
+            path_input: String::from("schematic.json"),
             active_element: Default::default(),
             param: Default::default(),
-            spmanager,
-            lib,
-            traces: None,
-            ac_hz: String::from("60"),
+            simulator,
+            plot: None,
+            run_started: None,
+            output: vec![],
+            run_output: vec![],
+            table: None,
+            card: String::new(),
+            sweep_points: None,
+            sim: None,
+            breakpoint: None,
+            paused_at: None,
+            dc: [
+                DcSweepInput {
+                    src: None,
+                    start: String::from("0"),
+                    stop: String::from("5"),
+                    step: String::from("0.1"),
+                },
+                DcSweepInput {
+                    src: None,
+                    start: String::from("0"),
+                    stop: String::from("1"),
+                    step: String::from("0.5"),
+                },
+            ],
+            out_net: None,
+            ref_net: String::from(GROUND),
+            in_src: None,
+            pz_in: None,
+            pz_ref: String::from(GROUND),
+            ac_scale: SweepScale::Dec,
+            ac_points: String::from("10"),
+            ac_start: String::from("1"),
+            ac_stop: String::from("1meg"),
             tran_step: String::from("10u"),
             tran_end: String::from("1m"),
+            stop_at: String::new(),
+        }
+    }
+}
+
+impl CircuitSchematicPage {
+    /// save the circuit to path and make path the current file path if successful
+    fn save(&mut self, path: PathBuf) {
+        match self.viewport.content.content.save(&path) {
+            Ok(()) => {
+                self.status = format!("saved {}", path.display());
+                self.path_input = path.display().to_string();
+                self.file_path = Some(path);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+    /// netlist the circuit and load it into ngspice along with the analysis card. On netlisting errors,
+    /// select the offending devices, report the errors in the status and return false
+    fn load_netlist(&mut self, analysis: String) -> bool {
+        self.viewport.content.content.prune();
+        match self.viewport.content.content.netlist() {
+            Ok(netlist) => {
+                let mut lines: Vec<String> = netlist.lines().map(String::from).collect();
+                lines.push(analysis);
+                lines.push(String::from(".end"));
+                let loaded = match &mut self.simulator {
+                    Some(simulator) => simulator.load(&lines),
+                    None => Err(String::from("simulation disabled")),
+                };
+                match loaded {
+                    Ok(()) => {
+                        self.status.clear();
+                        true
+                    }
+                    Err(e) => {
+                        self.status = e;
+                        false
+                    }
+                }
+            }
+            Err(errors) => {
+                let devices = self
+                    .viewport
+                    .content
+                    .content
+                    .find_devices(errors.iter().map(|e| e.device()));
+                self.viewport.content.select(devices);
+                self.viewport.passive_cache.clear();
+                self.status = errors
+                    .iter()
+                    .map(|e| e.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                false
+            }
+        }
+    }
+    /// returns the analysis of sim from the values entered, or an error message if a value is missing or does not parse
+    fn analysis(&self, sim: Sim) -> Result<Analysis, String> {
+        let value = |name: &str, s: &str| {
+            parse_spice_number(s).ok_or_else(|| format!("invalid {}: {}", name, s))
+        };
+        let freq_sweep = || -> Result<_, String> {
+            Ok(FreqSweep {
+                scale: self.ac_scale,
+                points: self
+                    .ac_points
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid points: {}", self.ac_points))?,
+                fstart: value("start frequency", &self.ac_start)?,
+                fstop: value("stop frequency", &self.ac_stop)?,
+            })
+        };
+        // output voltage across the small-signal output and reference nets
+        let output_var = || -> Result<String, String> {
+            let output = self
+                .out_net
+                .as_ref()
+                .ok_or_else(|| String::from("select an output net"))?;
+            Ok(if self.ref_net == GROUND {
+                format!("v({})", output)
+            } else {
+                format!("v({},{})", output, self.ref_net)
+            })
+        };
+        let analysis = match sim {
+            Sim::DcOp => Analysis::Op,
+            Sim::Dc => {
+                let sweep = |input: &DcSweepInput, prefix: &str| -> Result<_, String> {
+                    let src = match &input.src {
+                        Some(src) => src.clone(),
+                        None => return Ok(None),
+                    };
+                    Ok(Some(DcSweep {
+                        src,
+                        start: value(&format!("{}start", prefix), &input.start)?,
+                        stop: value(&format!("{}stop", prefix), &input.stop)?,
+                        step: value(&format!("{}step", prefix), &input.step)?,
+                    }))
+                };
+                let DcSweep {
+                    src,
+                    start,
+                    stop,
+                    step,
+                } = sweep(&self.dc[0], "dc ")?
+                    .ok_or_else(|| String::from("select a dc sweep source"))?;
+                Analysis::Dc {
+                    src,
+                    start,
+                    stop,
+                    step,
+                    nested: sweep(&self.dc[1], "nested ")?,
+                }
+            }
+            Sim::Ac => Analysis::Ac(freq_sweep()?),
+            Sim::Noise => Analysis::Noise {
+                output: self
+                    .out_net
+                    .clone()
+                    .ok_or_else(|| String::from("select an output net"))?,
+                ref_node: Some(self.ref_net.clone()).filter(|n| n != GROUND),
+                src: self
+                    .in_src
+                    .clone()
+                    .ok_or_else(|| String::from("select an input source"))?,
+                sweep: freq_sweep()?,
+                pts_per_summary: None,
+            },
+            Sim::Tf => Analysis::Tf {
+                output: output_var()?,
+                src: self
+                    .in_src
+                    .clone()
+                    .ok_or_else(|| String::from("select an input source"))?,
+            },
+            Sim::Sens(ac) => Analysis::Sens {
+                output: output_var()?,
+                sweep: if ac { Some(freq_sweep()?) } else { None },
+            },
+            Sim::Pz => Analysis::Pz {
+                in_pos: self
+                    .pz_in
+                    .clone()
+                    .ok_or_else(|| String::from("select a pole-zero input net"))?,
+                in_neg: self.pz_ref.clone(),
+                out_pos: self
+                    .out_net
+                    .clone()
+                    .ok_or_else(|| String::from("select an output net"))?,
+                out_neg: self.ref_net.clone(),
+                transfer: PzTransfer::Vol,
+                kind: PzKind::Both,
+            },
+            Sim::Tran => Analysis::Tran {
+                tstep: value("time step", &self.tran_step)?,
+                tstop: value("stop time", &self.tran_end)?,
+                tstart: None,
+                tmax: None,
+                uic: false,
+            },
+        };
+        Ok(analysis)
+    }
+    /// start sim in the ngspice background thread, unless a simulation is already running
+    fn run(&mut self, sim: Sim) {
+        if self.sim.is_some() {
+            return;
+        }
+        if self.simulator.is_none() {
+            self.status = String::from("simulation disabled - ngspice could not be loaded");
+            return;
+        }
+        let analysis = match self.analysis(sim) {
+            Ok(analysis) => analysis,
+            Err(e) => {
+                self.status = e;
+                return;
+            }
+        };
+        let card = match analysis.card() {
+            Ok(card) => card,
+            Err(e) => {
+                self.status = e.to_string();
+                return;
+            }
+        };
+        self.sweep_points = analysis.sweep_points();
+        self.collect_output();
+        self.run_output.clear();
+        self.run_started = Some(card.clone());
+        self.card = card.clone();
+        if !self.load_netlist(card) {
+            self.diagnose();
+            return;
+        }
+        self.breakpoint = None;
+        self.paused_at = None;
+        if let Sim::Tran = sim {
+            self.set_breakpoint(None);
+        }
+        if let Some(simulator) = &mut self.simulator {
+            if let Err(e) = simulator.run() {
+                self.status = e;
+                self.diagnose();
+                return;
+            }
+        }
+        self.sim = Some(sim);
+    }
+    /// returns true if the background simulation has finished
+    fn sim_finished(&mut self) -> bool {
+        !self
+            .simulator
+            .as_mut()
+            .map_or(false, |simulator| simulator.is_running())
+    }
+    /// set a breakpoint at the stop time, if it parses and comes after the simulation time after
+    fn set_breakpoint(&mut self, after: Option<f64>) {
+        self.breakpoint = parse_spice_number(&self.stop_at)
+            .filter(|t| match after {
+                Some(after) => *t > after,
+                None => true,
+            })
+            .filter(|t| {
+                self.simulator
+                    .as_mut()
+                    .map_or(false, |simulator| simulator.set_breakpoint(*t))
+            });
+    }
+    /// if the finished background simulation stopped at a breakpoint rather than completing, display the node voltages
+    /// on the schematic and return the simulation time at which it stopped
+    fn breakpoint_reached(&mut self) -> Option<f64> {
+        let breakpoint = self.breakpoint?;
+        let end = parse_spice_number(&self.tran_end)?;
+        // a simulation which failed also ends early
+        self.collect_output();
+        if self.run_output.iter().any(|line| match line {
+            OutputLine::Stderr(s) => s.to_ascii_lowercase().contains("error"),
+            _ => false,
+        }) {
+            return None;
+        }
+        let plot = self.simulator.as_mut()?.results().ok()?;
+        let time = plot.get("time")?.data.real();
+        let t = *time.last()?;
+        // ngspice stops at the breakpoint, allowing for rounding of the time
+        if t >= end || t < breakpoint * (1.0 - 1e-9) {
+            return None;
+        }
+        self.viewport.update(CompositeMsg {
+            content_msg: schematic::Msg::ContentMsg(Msg::DcOp(plot_values(&plot, time.len() - 1))),
+            viewport_msg: viewport::Msg::None,
+        });
+        Some(t)
+    }
+    /// plot the noise density and report the integrated noise of the finished noise analysis
+    fn noise_results(&mut self) {
+        let plots = match self
+            .simulator
+            .as_mut()
+            .map(|simulator| simulator.all_results())
+        {
+            Some(Ok(plots)) => plots,
+            Some(Err(e)) => {
+                self.status = e;
+                return;
+            }
+            None => return,
+        };
+        let has = |name: &str| plots.iter().find(|p| p.get(name).is_some());
+        let spectrum = match has("onoise_spectrum") {
+            Some(spectrum) => spectrum,
+            // simulation may have failed or been stopped before the first point
+            None => return,
+        };
+        let (output, input) = noise_traces(spectrum);
+        let summary = has("onoise_total").map_or(String::new(), noise_summary);
+        self.status = summary.clone();
+        self.plot = Some(PlotPageMsg::Noise(output, input, summary));
+    }
+    /// process the results of the finished simulation sim
+    fn sim_results(&mut self, sim: Sim) {
+        if let Sim::Noise = sim {
+            self.noise_results();
+            return;
+        }
+        let plot = match self.simulator.as_mut().map(|simulator| simulator.results()) {
+            Some(Ok(plot)) => plot,
+            Some(Err(e)) => {
+                self.status = e;
+                return;
+            }
+            None => return,
+        };
+        // simulation may have failed or been stopped before the first point
+        let last = match plot.vecs.iter().map(|v| v.data.len()).max() {
+            Some(len) if len > 0 => len - 1,
+            _ => return,
+        };
+        match sim {
+            Sim::DcOp => {
+                self.viewport.update(CompositeMsg {
+                    content_msg: schematic::Msg::ContentMsg(Msg::DcOp(plot_values(&plot, last))),
+                    viewport_msg: viewport::Msg::None,
+                });
+            }
+            Sim::Dc => {
+                // node voltages against the swept source
+                let mut plot = plot;
+                plot.vecs
+                    .retain(|v| v.scale.is_none() || v.unit == SimUnit::Voltage);
+                self.plot = Some(PlotPageMsg::Traces(plot_traces(&plot, self.sweep_points)));
+            }
+            Sim::Ac => {
+                // port voltages at the stop frequency
+                self.viewport.update(CompositeMsg {
+                    content_msg: schematic::Msg::ContentMsg(Msg::Ac(plot_values(&plot, last))),
+                    viewport_msg: viewport::Msg::None,
+                });
+                let (magnitude, phase) = bode_traces(&plot);
+                self.plot = Some(PlotPageMsg::Bode(magnitude, phase));
+            }
+            Sim::Noise => {}
+            Sim::Tf | Sim::Sens(_) => {
+                let title = self.card.clone();
+                let circuit = &self.viewport.content.content;
+                // sens names device parameters e.g. `m1:w` or `m1_w`, tf the input source e.g. `v1#input_impedance`
+                let device_of = |name: &str| {
+                    std::iter::once(name)
+                        .chain(
+                            name.rmatch_indices([':', '_', '#'])
+                                .map(|(i, _)| &name[..i]),
+                        )
+                        .find(|id| !circuit.find_devices([*id]).is_empty())
+                        .map(String::from)
+                };
+                self.table = Some(ResultTable::new(title, &plot, device_of));
+            }
+            Sim::Pz => {
+                let (poles, zeros) = pz_values(&plot);
+                self.plot = Some(PlotPageMsg::PoleZero(poles, zeros));
+            }
+            Sim::Tran => {
+                self.plot = Some(PlotPageMsg::Traces(plot_traces(&plot, None)));
+            }
+        }
+    }
+    /// send cmd to the simulator, unless a simulation is running
+    pub fn command(&mut self, cmd: &str) -> Result<(), String> {
+        if self.sim.is_some() && self.paused_at.is_none() {
+            return Err(String::from("simulation running - stop it first"));
+        }
+        match &mut self.simulator {
+            Some(simulator) => simulator.command(cmd),
+            None => Err(String::from("simulation disabled")),
+        }
+    }
+    /// take the simulator output produced since the last call
+    pub fn take_output(&mut self) -> Vec<OutputLine> {
+        self.collect_output();
+        std::mem::take(&mut self.output)
+    }
+    /// move the output of the simulator to the console buffer, keeping a copy for diagnostics
+    fn collect_output(&mut self) {
+        if let Some(simulator) = &mut self.simulator {
+            let output = simulator.take_output();
+            self.run_output.extend(output.iter().cloned());
+            self.output.extend(output);
+        }
+    }
+    /// select the devices and nets named in the problems ngspice reported during the last simulation,
+    /// and report those problems in the status
+    fn diagnose(&mut self) {
+        self.collect_output();
+        let circuit = &self.viewport.content.content;
+        let mut atoms = HashSet::new();
+        let mut messages = vec![];
+        for d in diagnostics(&self.run_output) {
+            let found: HashSet<_> = circuit
+                .find_devices(d.devices.iter().map(String::as_str))
+                .into_iter()
+                .chain(circuit.find_nets(d.nodes.iter().map(String::as_str)))
+                .collect();
+            if !found.is_empty() {
+                atoms.extend(found);
+                messages.push(d.message);
+            }
+        }
+        if atoms.is_empty() {
+            return;
+        }
+        self.viewport.content.select(atoms);
+        self.viewport.passive_cache.clear();
+        self.status = messages.join("; ");
+    }
+    /// polls the background simulation while one is running
+    pub fn subscription(&self) -> Subscription<CircuitPageMsg> {
+        if self.sim.is_some() && self.paused_at.is_none() {
+            iced::time::every(Duration::from_millis(100)).map(|_| CircuitPageMsg::SimPoll)
+        } else {
+            Subscription::none()
         }
     }
 }
@@ -161,6 +638,7 @@ impl Default for CircuitSchematicPage {
 impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
     fn update(&mut self, msg: CircuitPageMsg) {
         const NO_MODIFIER: Modifiers = Modifiers::empty();
+        const CTRL_SHIFT: Modifiers = Modifiers::CTRL.union(Modifiers::SHIFT);
         match msg {
             CircuitPageMsg::ParamChanged(s) => {
                 self.param = s;
@@ -176,6 +654,8 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }
                         CircuitAtom::RcRLabel(l) => {
                             l.0.borrow_mut().set_name(self.param.clone());
+                            // renamed label may connect or disconnect nets
+                            self.viewport.content.content.prune();
                         }
                     }
                     self.viewport.passive_cache.clear();
@@ -190,22 +670,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.lib.command("op"); // ngspice recommends sending in control statements separately, not as part of netlist
-                        if let Some(pkvecvaluesall) =
-                            self.spmanager.vecvals.try_lock().unwrap().pop()
-                        {
-                            self.viewport.update(CompositeMsg {
-                                content_msg: schematic::Msg::ContentMsg(Msg::DcOp(
-                                    pkvecvaluesall.clone(),
-                                )),
-                                viewport_msg: viewport::Msg::None,
-                            });
-                        }
+                        self.run(Sim::DcOp);
                     }
                     schematic::Msg::Event(
                         Event::Keyboard(iced::keyboard::Event::KeyPressed {
@@ -214,23 +679,61 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.lib
-                            .command(&format!("ac lin 0 {} {}", self.ac_hz, self.ac_hz)); // ngspice recommends sending in control statements separately, not as part of netlist
-                        if let Some(pkvecvaluesall) =
-                            self.spmanager.vecvals.try_lock().unwrap().pop()
-                        {
-                            self.viewport.update(CompositeMsg {
-                                content_msg: schematic::Msg::ContentMsg(Msg::Ac(
-                                    pkvecvaluesall.clone(),
-                                )),
-                                viewport_msg: viewport::Msg::None,
-                            });
-                        }
+                        self.run(Sim::Ac);
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::D,
+                            modifiers: iced::keyboard::Modifiers::SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Dc);
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::N,
+                            modifiers: iced::keyboard::Modifiers::SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Noise);
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::F,
+                            modifiers: iced::keyboard::Modifiers::SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Tf);
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::S,
+                            modifiers: iced::keyboard::Modifiers::SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Sens(false));
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::S,
+                            modifiers: CTRL_SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Sens(true));
+                    }
+                    schematic::Msg::Event(
+                        Event::Keyboard(iced::keyboard::Event::KeyPressed {
+                            key_code: iced::keyboard::KeyCode::P,
+                            modifiers: iced::keyboard::Modifiers::SHIFT,
+                        }),
+                        _,
+                    ) => {
+                        self.run(Sim::Pz);
                     }
                     schematic::Msg::Event(
                         Event::Keyboard(iced::keyboard::Event::KeyPressed {
@@ -239,41 +742,7 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                         }),
                         _,
                     ) => {
-                        self.viewport.update(CompositeMsg {
-                            content_msg: schematic::Msg::ContentMsg(Msg::NetList),
-                            viewport_msg: viewport::Msg::None,
-                        });
-                        self.lib.command("source netlist.cir"); // results pointer array starts at same address
-                        self.spmanager.vecvals.try_lock().unwrap().clear();
-                        self.lib
-                            .command(&format!("tran {} {}", self.tran_step, self.tran_end)); // ngspice recommends sending in control statements separately, not as part of netlist
-
-                        let pk_results = self.spmanager.vecvals.try_lock().unwrap();
-
-                        let trace_count = pk_results.first().unwrap().count as usize;
-                        let mut results: Vec<Vec<VSPoint>> = Vec::with_capacity(trace_count);
-                        for _ in 0..trace_count {
-                            results.push(Vec::with_capacity(pk_results.len()));
-                        }
-
-                        let x_i = pk_results
-                            .first()
-                            .unwrap()
-                            .vecsa
-                            .iter()
-                            .position(|x| x.name == "time")
-                            .unwrap();
-                        for step_val in pk_results.iter() {
-                            for (trace_i, trace_val) in step_val.vecsa.iter().enumerate() {
-                                results[trace_i].push(VSPoint::new(
-                                    step_val.vecsa[x_i].creal as f32,
-                                    trace_val.creal as f32,
-                                ));
-                            }
-                        }
-                        results.remove(x_i);
-
-                        self.traces = Some(results);
+                        self.run(Sim::Tran);
                     }
                     _ => {
                         self.viewport.update(msgs);
@@ -298,9 +767,115 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
 
                 self.net_name = self.viewport.content.content.infobarstr.take();
             }
-            CircuitPageMsg::HzChanged(s) => self.ac_hz = s,
+            CircuitPageMsg::DcSrcSel(i, src) => {
+                self.dc[i].src = Some(src).filter(|s| s != NO_SOURCE)
+            }
+            CircuitPageMsg::DcStartChanged(i, s) => self.dc[i].start = s,
+            CircuitPageMsg::DcStopChanged(i, s) => self.dc[i].stop = s,
+            CircuitPageMsg::DcStepChanged(i, s) => self.dc[i].step = s,
+            CircuitPageMsg::OutNetSel(net) => self.out_net = Some(net),
+            CircuitPageMsg::RefNetSel(net) => self.ref_net = net,
+            CircuitPageMsg::InSrcSel(src) => self.in_src = Some(src).filter(|s| s != NO_SOURCE),
+            CircuitPageMsg::AcScaleSel(scale) => self.ac_scale = scale,
+            CircuitPageMsg::AcPointsChanged(s) => self.ac_points = s,
+            CircuitPageMsg::AcStartChanged(s) => self.ac_start = s,
+            CircuitPageMsg::AcStopChanged(s) => self.ac_stop = s,
             CircuitPageMsg::StepChanged(s) => self.tran_step = s,
             CircuitPageMsg::TranChanged(s) => self.tran_end = s,
+            CircuitPageMsg::StopAtChanged(s) => self.stop_at = s,
+            CircuitPageMsg::PathChanged(s) => self.path_input = s,
+            CircuitPageMsg::Open => {
+                let path = PathBuf::from(&self.path_input);
+                match Circuit::load(&path) {
+                    Ok(circuit) => {
+                        self.viewport.content = Schematic::default();
+                        self.viewport.content.content = circuit;
+                        self.viewport.passive_cache.clear();
+                        self.active_element = None;
+                        self.param.clear();
+                        self.status = format!("opened {}", path.display());
+                        self.file_path = Some(path);
+                    }
+                    Err(e) => self.status = e.to_string(),
+                }
+            }
+            CircuitPageMsg::Save => {
+                let path = self
+                    .file_path
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(&self.path_input));
+                self.save(path);
+            }
+            CircuitPageMsg::SaveAs => {
+                self.save(PathBuf::from(&self.path_input));
+            }
+            CircuitPageMsg::SimPoll => {
+                if let Some(sim) = self.sim {
+                    if self.sim_finished() {
+                        if let Some(t) = self.breakpoint_reached() {
+                            self.paused_at = Some(t);
+                            self.status = format!("paused at {:e} s", t);
+                        } else {
+                            self.sim = None;
+                            self.breakpoint = None;
+                            self.status.clear();
+                            self.sim_results(sim);
+                            self.diagnose();
+                        }
+                    } else if let Some((name, pct)) = self
+                        .simulator
+                        .as_ref()
+                        .and_then(|simulator| simulator.progress())
+                    {
+                        self.status = format!("{}: {:.1}%", name, pct);
+                    }
+                }
+            }
+            CircuitPageMsg::SimStop => {
+                if self.sim.take().is_some() {
+                    // a paused simulation is already halted
+                    if let (None, Some(simulator)) = (self.paused_at.take(), &mut self.simulator) {
+                        simulator.halt();
+                    }
+                    self.breakpoint = None;
+                    self.status = String::from("simulation stopped");
+                }
+            }
+            CircuitPageMsg::SimContinue => {
+                if let Some(t) = self.paused_at.take() {
+                    self.set_breakpoint(Some(t));
+                    let resumed = self
+                        .simulator
+                        .as_mut()
+                        .map_or(false, |simulator| simulator.resume());
+                    if resumed {
+                        self.status.clear();
+                    } else {
+                        // still paused, so that it may be stopped
+                        self.paused_at = Some(t);
+                        self.status = String::from("simulation could not be continued");
+                    }
+                }
+            }
+            CircuitPageMsg::PzInSel(net) => self.pz_in = Some(net),
+            CircuitPageMsg::PzRefSel(net) => self.pz_ref = net,
+            CircuitPageMsg::TableEvt(TableMsg::Select(i)) => {
+                let table = match &self.table {
+                    Some(table) => table,
+                    None => return,
+                };
+                if let Some(id) = table.device(i) {
+                    let devices = self.viewport.content.content.find_devices([id]);
+                    self.viewport.content.select(devices);
+                    self.viewport.passive_cache.clear();
+                }
+            }
+            CircuitPageMsg::TableEvt(TableMsg::Sort(column)) => {
+                if let Some(table) = &mut self.table {
+                    table.sort_by(column);
+                }
+            }
+            CircuitPageMsg::TableEvt(TableMsg::Close) => self.table = None,
         }
     }
 
@@ -310,7 +885,14 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
             self.viewport.content.content.curpos_ssp().x,
             self.viewport.content.content.curpos_ssp().y
         );
-        let canvas = self.viewport.view().map(CircuitPageMsg::ViewportEvt);
+        let canvas: Element<CircuitPageMsg> = match &self.table {
+            Some(table) => row![
+                self.viewport.view().map(CircuitPageMsg::ViewportEvt),
+                table.view().map(CircuitPageMsg::TableEvt),
+            ]
+            .into(),
+            None => self.viewport.view().map(CircuitPageMsg::ViewportEvt),
+        };
         let infobar = row![
             iced::widget::text(str_ssp)
                 .size(16)
@@ -324,17 +906,51 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
                 .size(16)
                 .height(16)
                 .vertical_alignment(iced::alignment::Vertical::Center),
+            iced::widget::text(&self.status)
+                .size(16)
+                .height(16)
+                .vertical_alignment(iced::alignment::Vertical::Center),
+            iced::widget::text(
+                self.viewport
+                    .content
+                    .content
+                    .label_conflicts
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )
+            .size(16)
+            .height(16)
+            .style(iced::Color::from_rgb(1.0, 0.3, 0.3))
+            .vertical_alignment(iced::alignment::Vertical::Center),
         ]
         .spacing(10);
+        let filebar = row![
+            text("file: "),
+            text_input("", &self.path_input)
+                .width(iced::Length::Fill)
+                .on_input(CircuitPageMsg::PathChanged),
+            button("open").on_press(CircuitPageMsg::Open),
+            button("save").on_press(CircuitPageMsg::Save),
+            button("save as").on_press(CircuitPageMsg::SaveAs),
+        ]
+        .width(Length::Fill);
+        let stop = if self.sim.is_some() {
+            button("stop").on_press(CircuitPageMsg::SimStop)
+        } else {
+            button("stop") // disabled while no simulation is running
+        };
+        let cont = if self.paused_at.is_some() {
+            button("continue").on_press(CircuitPageMsg::SimContinue)
+        } else {
+            button("continue") // disabled while no simulation is paused
+        };
         let toolbar = row![
             // button("wire").on_press(CircuitPageMsg::ViewportEvt(viewport::CompositeMsg {
             //     content_msg: schematic::Msg::ContentMsg(Msg::Wire),
             //     viewport_msg: viewport::Msg::None,
             // })),
-            text("ac freq (Hz): "),
-            text_input("", &self.ac_hz)
-                .width(50)
-                .on_input(CircuitPageMsg::HzChanged),
             text("tran step (S): "),
             text_input("", &self.tran_step)
                 .width(50)
@@ -343,6 +959,12 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
             text_input("", &self.tran_end)
                 .width(50)
                 .on_input(CircuitPageMsg::TranChanged),
+            text("stop at (S): "),
+            text_input("", &self.stop_at)
+                .width(50)
+                .on_input(CircuitPageMsg::StopAtChanged),
+            stop,
+            cont,
             text("Param: "),
             text_input("", &self.param)
                 .width(iced::Length::Fill)
@@ -351,7 +973,101 @@ impl IcedStruct<CircuitPageMsg> for CircuitSchematicPage {
         ]
         .width(Length::Fill);
 
-        let schematic = iced::widget::column![canvas, infobar, toolbar];
+        let acbar = row![
+            text("freq sweep (ac, noise, ac sens): "),
+            pick_list(
+                &[SweepScale::Dec, SweepScale::Oct, SweepScale::Lin][..],
+                Some(self.ac_scale),
+                CircuitPageMsg::AcScaleSel,
+            ),
+            text(" points: "),
+            text_input("", &self.ac_points)
+                .width(50)
+                .on_input(CircuitPageMsg::AcPointsChanged),
+            text(" start (Hz): "),
+            text_input("", &self.ac_start)
+                .width(50)
+                .on_input(CircuitPageMsg::AcStartChanged),
+            text(" stop (Hz): "),
+            text_input("", &self.ac_stop)
+                .width(50)
+                .on_input(CircuitPageMsg::AcStopChanged),
+        ]
+        .width(Length::Fill);
+
+        let mut sources = vec![String::from(NO_SOURCE)];
+        sources.extend(self.viewport.content.content.source_ids());
+        let dc_sweep = |i: usize, label: &'static str| {
+            let input = &self.dc[i];
+            row![
+                text(label),
+                pick_list(
+                    sources.clone(),
+                    Some(input.src.clone().unwrap_or_else(|| String::from(NO_SOURCE))),
+                    move |src| CircuitPageMsg::DcSrcSel(i, src),
+                ),
+                text(" start: "),
+                text_input("", &input.start)
+                    .width(50)
+                    .on_input(move |s| CircuitPageMsg::DcStartChanged(i, s)),
+                text(" stop: "),
+                text_input("", &input.stop)
+                    .width(50)
+                    .on_input(move |s| CircuitPageMsg::DcStopChanged(i, s)),
+                text(" step: "),
+                text_input("", &input.step)
+                    .width(50)
+                    .on_input(move |s| CircuitPageMsg::DcStepChanged(i, s)),
+            ]
+        };
+        let dcbar = row![dc_sweep(0, "dc sweep: "), dc_sweep(1, "  nested: ")].width(Length::Fill);
+
+        let nets = self.viewport.content.content.net_names();
+        let mut ref_nets = vec![String::from(GROUND)];
+        ref_nets.extend(nets.iter().filter(|n| *n != GROUND).cloned());
+        let smallsignalbar = row![
+            text("output (noise, tf, sens, pz): "),
+            pick_list(
+                nets.clone(),
+                self.out_net.clone(),
+                CircuitPageMsg::OutNetSel
+            ),
+            text(" reference: "),
+            pick_list(
+                ref_nets.clone(),
+                Some(self.ref_net.clone()),
+                CircuitPageMsg::RefNetSel
+            ),
+            text(" input source: "),
+            pick_list(
+                sources,
+                Some(
+                    self.in_src
+                        .clone()
+                        .unwrap_or_else(|| String::from(NO_SOURCE))
+                ),
+                CircuitPageMsg::InSrcSel,
+            ),
+            text(" pz input: "),
+            pick_list(nets, self.pz_in.clone(), CircuitPageMsg::PzInSel),
+            text(" reference: "),
+            pick_list(
+                ref_nets,
+                Some(self.pz_ref.clone()),
+                CircuitPageMsg::PzRefSel
+            ),
+        ]
+        .width(Length::Fill);
+
+        let schematic = iced::widget::column![
+            canvas,
+            infobar,
+            toolbar,
+            dcbar,
+            acbar,
+            smallsignalbar,
+            filebar
+        ];
 
         schematic.into()
     }
@@ -19,7 +19,7 @@ use send_wrapper::SendWrapper;
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use std::{collections::HashSet, fs};
+use std::collections::HashSet;
 
 mod gui;
 pub use gui::CircuitPageMsg;
@@ -28,8 +28,16 @@ pub use gui::CircuitSchematicPage;
 mod atoms;
 pub use atoms::CircuitAtom;
 
+mod file;
+
+mod netlist;
+pub use netlist::NetlistError;
+
+mod table;
+
 use super::layers::DevicesLayer;
 use super::layers::DijkstraSt;
+use super::layers::LabelConflict;
 use super::layers::NetLabelsLayer;
 use super::layers::NetsLayer;
 use super::layers::SchematicLayerEnum;
@@ -38,7 +46,6 @@ use super::layers::SchematicLayerEnum;
 pub enum Msg {
     CanvasEvent(Event),
     Wire,
-    NetList,
     DcOp(PkVecvaluesall),
     Ac(PkVecvaluesall),
 }
@@ -71,6 +78,9 @@ pub struct Circuit {
     curpos_ssp: SSPoint,
 
     device_models: NgModels,
+
+    /// nets with more than one distinct net label, found during the last prune
+    pub label_conflicts: Vec<LabelConflict>,
 }
 
 impl Default for Circuit {
@@ -88,6 +98,7 @@ impl Default for Circuit {
             ]),
             curpos_ssp: Default::default(),
             device_models: Default::default(),
+            label_conflicts: Default::default(),
         }
     }
 }
@@ -304,12 +315,12 @@ impl schematic::Content<CircuitAtom, Msg> for Circuit {
                             if ssp == dijkst.start() {
                                 new_ws = None;
                             } else if self.electrically_occupies_ssp(ssp) {
-                                let extra_vertices = self.devices_layer().ports_ssp();
-                                self.nets_layer_mut().merge(g.as_ref(), &extra_vertices);
+                                self.nets_layer_mut().merge(g.as_ref());
+                                self.prune();
                                 new_ws = None;
                             } else {
-                                let extra_vertices = self.devices_layer().ports_ssp();
-                                self.nets_layer_mut().merge(g.as_ref(), &extra_vertices);
+                                self.nets_layer_mut().merge(g.as_ref());
+                                self.prune();
                                 new_ws = Some((Box::new(Nets::new()), DijkstraSt::new(ssp)));
                             }
                             ret_msg_tmp = SchematicMsg::ClearPassive;
@@ -446,10 +457,6 @@ impl schematic::Content<CircuitAtom, Msg> for Circuit {
                 self.state = state;
                 ret_msg_tmp
             }
-            Msg::NetList => {
-                self.netlist();
-                SchematicMsg::None
-            }
             Msg::Wire => {
                 self.state = CircuitSt::Wiring(None);
                 SchematicMsg::None
@@ -571,26 +578,20 @@ impl schematic::Content<CircuitAtom, Msg> for Circuit {
 }
 
 impl Circuit {
-    /// create netlist for the current schematic and save it.
-    pub fn netlist(&mut self) {
-        let mut netlist = String::from("Netlist Created by Circe\n");
-        netlist.push_str(&self.device_models.model_definitions());
-        if self.devices_layer().get_set().is_empty() {
-            // empty netlist
-            netlist.push_str("V_0 0 n1 0"); // give it something so spice doesnt hang
-            return;
-        }
-
-        self.prune();
-        for d in self.devices_layer().get_set() {
-            netlist.push_str(&d.0.borrow_mut().spice_line(self.nets_layer()));
-        }
-        netlist.push('\n');
-        fs::write("netlist.cir", netlist.as_bytes()).expect("Unable to write file");
-    }
     /// clear up nets graph: merging segments, cleaning up segment net names, etc.
-    fn prune(&mut self) {
+    /// net labels name the net they are placed on, conflicts are kept in `label_conflicts`.
+    /// edits prune the schematic as they are made - prune before `netlist` to include every device placed since
+    pub fn prune(&mut self) {
         let extra_vertices = self.devices_layer().ports_ssp();
-        self.nets_layer_mut().prune(&extra_vertices);
+        let user_labels: Vec<_> = self
+            .labels_layer()
+            .get_set()
+            .iter()
+            .map(|l| {
+                let l = l.0.borrow();
+                (l.pos(), Rc::new(l.read().to_string()))
+            })
+            .collect();
+        self.label_conflicts = self.nets_layer_mut().prune(&extra_vertices, &user_labels);
     }
 }
@@ -0,0 +1,122 @@
+//! Netlister
+//! creates the spice netlist of a circuit without touching the filesystem
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::schematic::atoms::DeviceClass;
+use crate::transforms::SSPoint;
+
+use super::{Circuit, CircuitAtom};
+
+/// errors which prevent a circuit from being netlisted
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetlistError {
+    /// device port is not on any net, as of the last `Circuit::prune`.
+    /// pruning gives every port a net, so this is reported for devices placed since.
+    UnconnectedPort {
+        /// netlist identifier of the device, e.g. R1
+        device: String,
+        /// schematic coordinate of the port
+        port: SSPoint,
+    },
+}
+
+impl NetlistError {
+    /// returns the netlist identifier of the offending device
+    pub fn device(&self) -> &str {
+        match self {
+            NetlistError::UnconnectedPort { device, .. } => device,
+        }
+    }
+}
+
+impl fmt::Display for NetlistError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetlistError::UnconnectedPort { device, port } => {
+                write!(
+                    f,
+                    "{}: unconnected port at ({}, {})",
+                    device, port.x, port.y
+                )
+            }
+        }
+    }
+}
+
+impl Circuit {
+    /// returns the netlist of the current schematic, or every error found while netlisting.
+    /// nets are netlisted as of the last `prune`, which is left to the caller.
+    pub fn netlist(&self) -> Result<String, Vec<NetlistError>> {
+        let mut netlist = String::from("Netlist Created by Circe\n");
+        netlist.push_str(&self.device_models.model_definitions());
+        if self.devices_layer().get_set().is_empty() {
+            // empty netlist
+            netlist.push_str("V_0 0 n1 0\n"); // give it something so spice doesnt hang
+            return Ok(netlist);
+        }
+
+        let mut errors = vec![];
+        let mut lines = vec![];
+        for d in self.devices_layer().get_set() {
+            let mut device = d.0.borrow_mut();
+            match device.spice_line(self.nets_layer()) {
+                Ok(line) => lines.push(line),
+                Err(ports) => {
+                    errors.extend(ports.into_iter().map(|port| NetlistError::UnconnectedPort {
+                        device: device.ng_id(),
+                        port,
+                    }));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        // sort for a deterministic netlist
+        lines.sort();
+        for line in lines {
+            netlist.push_str(&line);
+        }
+        netlist.push('\n');
+        Ok(netlist)
+    }
+
+    /// returns the devices with netlist identifiers in ids, as circuit atoms
+    pub fn find_devices<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> HashSet<CircuitAtom> {
+        ids.into_iter()
+            .filter_map(|id| self.devices_layer().find_by_ng_id(id))
+            .map(CircuitAtom::RcRDevice)
+            .collect()
+    }
+
+    /// returns the net segments of the nets named in names, as circuit atoms
+    pub fn find_nets<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> HashSet<CircuitAtom> {
+        names
+            .into_iter()
+            .flat_map(|name| self.nets_layer().edges_named(name))
+            .map(CircuitAtom::NetEdge)
+            .collect()
+    }
+
+    /// returns the netlist identifiers of the independent voltage and current sources, sorted
+    pub fn source_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .devices_layer()
+            .get_set()
+            .iter()
+            .map(|d| d.0.borrow())
+            .filter(|d| matches!(d.class(), DeviceClass::V(_) | DeviceClass::I(_)))
+            .map(|d| d.ng_id())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// returns the names of every net, sorted
+    pub fn net_names(&self) -> Vec<String> {
+        self.nets_layer().net_names()
+    }
+}
@@ -0,0 +1,174 @@
+//! Result table
+//! scalar simulation results (e.g. transfer function, sensitivities) listed next to the schematic
+
+use iced::widget::{button, column, row, scrollable, text};
+use iced::{Element, Length};
+use paprika::{SimData, SimPlot, SimUnit};
+
+#[derive(Debug, Clone)]
+pub enum TableMsg {
+    /// sort the rows by column, toggling the order if already sorted by it
+    Sort(Column),
+    /// row at index clicked
+    Select(usize),
+    /// close the table
+    Close,
+}
+
+/// sortable column of the result table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Name,
+    /// sorted by magnitude
+    Value,
+}
+
+/// a scalar result
+#[derive(Debug, Clone)]
+struct Row {
+    /// vector name, e.g. `transfer_function` or `r1`
+    name: String,
+    /// magnitude for complex results
+    value: f64,
+    /// phase (degrees) for complex results
+    phase: Option<f64>,
+    /// netlist identifier of the device the result belongs to, if any
+    device: Option<String>,
+}
+
+/// scalar results of a simulation
+#[derive(Debug, Clone)]
+pub struct ResultTable {
+    /// e.g. the analysis card
+    title: String,
+    /// rows in display order
+    rows: Vec<Row>,
+    /// frequency (Hz) of the values of an ac analysis, which are those at its first point
+    frequency: Option<f64>,
+    /// column the rows are sorted by, and true if ascending
+    sort: (Column, bool),
+}
+
+impl ResultTable {
+    /// table of every vector of plot other than frequency, complex values by their magnitude and phase at the first point.
+    /// a table is for a single point: for an ac sweep, the values are those at its first frequency, given in the header.
+    /// device_of returns the device identifier a vector name refers to, if any
+    pub fn new(title: String, plot: &SimPlot, device_of: impl Fn(&str) -> Option<String>) -> Self {
+        let rows = plot
+            .vecs
+            .iter()
+            .filter(|v| v.unit != SimUnit::Frequency)
+            .filter_map(|v| {
+                let (value, phase) = match &v.data {
+                    SimData::Real(d) => (*d.first()?, None),
+                    SimData::Complex(d) => {
+                        let c = d.first()?;
+                        (c.norm(), Some(c.arg().to_degrees()))
+                    }
+                };
+                Some(Row {
+                    name: v.name.clone(),
+                    value,
+                    phase,
+                    device: device_of(&v.name),
+                })
+            })
+            .collect();
+        let frequency = plot
+            .vecs
+            .iter()
+            .find(|v| v.unit == SimUnit::Frequency)
+            .and_then(|v| match &v.data {
+                SimData::Real(d) => d.first().copied(),
+                SimData::Complex(d) => d.first().map(|c| c.re),
+            });
+        let mut table = ResultTable {
+            title,
+            rows,
+            frequency,
+            sort: (Column::Name, true),
+        };
+        table.sort_rows();
+        table
+    }
+    /// returns the device identifier of the row at index i, if any
+    pub fn device(&self, i: usize) -> Option<&str> {
+        self.rows.get(i)?.device.as_deref()
+    }
+    fn sort_rows(&mut self) {
+        match self.sort.0 {
+            Column::Name => self.rows.sort_by(|a, b| a.name.cmp(&b.name)),
+            Column::Value => self
+                .rows
+                .sort_by(|a, b| a.value.abs().total_cmp(&b.value.abs())),
+        }
+        if !self.sort.1 {
+            self.rows.reverse();
+        }
+    }
+    /// sort by column, toggling the order if already sorted by it
+    pub fn sort_by(&mut self, column: Column) {
+        self.sort = if self.sort.0 == column {
+            (column, !self.sort.1)
+        } else {
+            (column, true)
+        };
+        self.sort_rows();
+    }
+
+    pub fn view(&self) -> Element<TableMsg> {
+        let arrow = |column: Column| match self.sort {
+            (c, true) if c == column => " ^",
+            (c, false) if c == column => " v",
+            _ => "",
+        };
+        let value = match self.frequency {
+            Some(f) => format!("|value| at {:.3e}Hz", f),
+            None => String::from("value"),
+        };
+        let has_phase = self.rows.iter().any(|r| r.phase.is_some());
+        let mut header = row![
+            button(text(format!("name{}", arrow(Column::Name))))
+                .width(Length::FillPortion(3))
+                .on_press(TableMsg::Sort(Column::Name)),
+            button(text(format!("{}{}", value, arrow(Column::Value))))
+                .width(Length::FillPortion(2))
+                .on_press(TableMsg::Sort(Column::Value)),
+        ];
+        if has_phase {
+            header = header.push(text("phase").width(Length::FillPortion(2)));
+        }
+        let rows: Vec<Element<TableMsg>> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                let mut cells = row![
+                    text(&r.name).size(14).width(Length::FillPortion(3)),
+                    text(format!("{:.4e}", r.value))
+                        .size(14)
+                        .width(Length::FillPortion(2)),
+                ];
+                if has_phase {
+                    let phase = r.phase.map_or(String::new(), |p| format!("{:.2}°", p));
+                    cells = cells.push(text(phase).size(14).width(Length::FillPortion(2)));
+                }
+                button(cells)
+                    .style(iced::theme::Button::Text)
+                    .width(Length::Fill)
+                    .on_press(TableMsg::Select(i))
+                    .into()
+            })
+            .collect();
+        column![
+            row![
+                text(&self.title).width(Length::Fill),
+                button("close").on_press(TableMsg::Close),
+            ],
+            header,
+            scrollable(column(rows)).height(Length::Fill),
+        ]
+        .width(if has_phase { 400 } else { 300 })
+        .into()
+    }
+}
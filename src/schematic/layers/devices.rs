@@ -29,6 +29,10 @@ impl ClassManager {
         self.wm += 1;
         self.wm
     }
+    /// raise the watermark to at least wm, so that IDs given out later do not collide with wm
+    pub fn bump(&mut self, wm: usize) {
+        self.wm = self.wm.max(wm);
+    }
 }
 
 /// struct to keep track of unique IDs for all devices of all types
@@ -61,6 +65,23 @@ impl Default for DevicesManager {
     }
 }
 
+impl DevicesManager {
+    /// returns the class manager responsible for the class of device
+    fn class_manager(&mut self, class: &DeviceClass) -> &mut ClassManager {
+        match class {
+            DeviceClass::Pm(_) => &mut self.pm,
+            DeviceClass::Nm(_) => &mut self.nm,
+            DeviceClass::Gnd(_) => &mut self.gnd,
+            DeviceClass::R(_) => &mut self.r,
+            DeviceClass::L(_) => &mut self.l,
+            DeviceClass::C(_) => &mut self.c,
+            DeviceClass::V(_) => &mut self.v,
+            DeviceClass::I(_) => &mut self.i,
+            DeviceClass::D(_) => &mut self.d,
+        }
+    }
+}
+
 /// struct containing all devices in schematic
 #[derive(Debug, Default, Clone)]
 pub struct Devices {
@@ -206,21 +227,17 @@ impl Devices {
     /// inserts device d into self.
     pub fn insert(&mut self, d: RcRDevice) {
         if !self.set.contains(&d) {
-            let ord = match d.0.borrow().class() {
-                DeviceClass::Pm(_) => self.manager.pm.incr(),
-                DeviceClass::Nm(_) => self.manager.nm.incr(),
-                DeviceClass::Gnd(_) => self.manager.gnd.incr(),
-                DeviceClass::R(_) => self.manager.r.incr(),
-                DeviceClass::L(_) => self.manager.l.incr(),
-                DeviceClass::C(_) => self.manager.c.incr(),
-                DeviceClass::V(_) => self.manager.v.incr(),
-                DeviceClass::I(_) => self.manager.i.incr(),
-                DeviceClass::D(_) => self.manager.d.incr(),
-            };
+            let ord = self.manager.class_manager(d.0.borrow().class()).incr();
             d.0.borrow_mut().set_wm(ord);
             self.set.insert(d);
         }
     }
+    /// inserts device d into self, keeping its existing ID. Used when loading a saved schematic.
+    pub fn insert_keep_id(&mut self, d: RcRDevice) {
+        let wm = d.0.borrow().wm();
+        self.manager.class_manager(d.0.borrow().class()).bump(wm);
+        self.set.insert(d);
+    }
     /// return vector of RcRDevice which intersects vsb
     pub fn intersects_vsb(&self, vsb: &VSBox) -> Vec<RcRDevice> {
         let ret: Vec<_> = self
@@ -311,6 +328,13 @@ impl Devices {
     pub fn get_set(&self) -> &HashSet<RcRDevice> {
         &self.set
     }
+    /// returns the device with netlist identifier id, if any. Comparison is case insensitive, as in ngspice
+    pub fn find_by_ng_id(&self, id: &str) -> Option<RcRDevice> {
+        self.set
+            .iter()
+            .find(|d| d.0.borrow().ng_id().eq_ignore_ascii_case(id))
+            .cloned()
+    }
 }
 
 impl Drawable for RcRDevice {
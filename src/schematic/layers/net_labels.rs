@@ -171,6 +171,9 @@ impl NetLabels {
     pub fn delete_item(&mut self, d: &RcRLabel) {
         self.set.remove(d);
     }
+    pub fn get_set(&self) -> &HashSet<RcRLabel> {
+        &self.set
+    }
     pub fn new_label() -> RcRLabel {
         RcRLabel::default()
     }
@@ -19,6 +19,8 @@ use crate::schematic::layers::nets::pathfinding::path_to_goal;
 use crate::schematic::layers::nets::pathfinding::wiring_pathfinder;
 
 mod pruning;
+pub use self::pruning::LabelConflict;
+
 /// This struct facillitates the creation of unique net names
 #[derive(Clone, Debug, Default)]
 struct LabelManager {
@@ -26,6 +28,8 @@ struct LabelManager {
     wm: usize,
     /// set of labels already in use
     pub labels: HashSet<Rc<String>>,
+    /// user net labels placed in the schematic, in lowercase, which are never generated
+    reserved: HashSet<String>,
 }
 
 impl LabelManager {
@@ -35,7 +39,7 @@ impl LabelManager {
         loop {
             let l = format!("net_{}", self.wm);
             self.wm += 1;
-            if !self.labels.contains(&l) {
+            if !self.labels.contains(&l) && !self.reserved.contains(&l) {
                 self.labels.insert(Rc::new(l.clone()));
                 break self.labels.get(&Rc::new(l)).unwrap().clone();
             }
@@ -46,6 +50,11 @@ impl LabelManager {
     fn register(&mut self, label: Rc<String>) {
         self.labels.insert(label);
     }
+    /// reserve the user net labels names, replacing those reserved before.
+    /// ngspice node names are case insensitive, so names are reserved regardless of case.
+    fn reserve<'a>(&mut self, names: impl IntoIterator<Item = &'a Rc<String>>) {
+        self.reserved = names.into_iter().map(|n| n.to_ascii_lowercase()).collect();
+    }
 }
 
 pub type NetsLayer = Box<Nets>;
@@ -182,6 +191,29 @@ impl Nets {
         }
         None
     }
+    /// returns the names of every net, sorted
+    pub fn net_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .graph
+            .all_edges()
+            .filter_map(|e| e.2.label.as_ref().map(|l| l.to_string()))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+    /// returns the NetEdges of the net named name. Comparison is case insensitive, as in ngspice
+    pub fn edges_named(&self, name: &str) -> Vec<NetEdge> {
+        self.graph
+            .all_edges()
+            .filter(|e| {
+                e.2.label
+                    .as_ref()
+                    .map_or(false, |l| l.eq_ignore_ascii_case(name))
+            })
+            .map(|e| e.2.clone())
+            .collect()
+    }
     /// return unique NetEdges intersecting with vsb
     pub fn intersects_vsbox(&mut self, vsb: &VSBox) -> Vec<NetEdge> {
         let mut ret = vec![];
@@ -222,8 +254,13 @@ impl Nets {
     }
     /// this function is called whenever schematic is changed. Ensures all connected nets have the same net name, overlapping segments are merged, etc.
     /// extra_vertices are coordinates where net segments should be bisected (device ports)
-    pub fn prune(&mut self, extra_vertices: &[SSPoint]) {
-        pruning::prune(self, extra_vertices);
+    /// user_labels are net labels (position, name) which name the net they are placed on. Returns nets with conflicting labels.
+    pub fn prune(
+        &mut self,
+        extra_vertices: &[SSPoint],
+        user_labels: &[(SSPoint, Rc<String>)],
+    ) -> Vec<LabelConflict> {
+        pruning::prune(self, extra_vertices, user_labels)
     }
     /// returns true if any net segment intersects with ssp
     pub fn occupies_ssp(&self, ssp: SSPoint) -> bool {
@@ -295,14 +332,13 @@ impl Nets {
             );
         }
     }
-    /// merge other into self. extra_vertices are coordinates where net segments should be bisected (device ports)
-    pub fn merge(&mut self, other: &Nets, extra_vertices: &[SSPoint]) {
+    /// merge other into self. Should be followed by `prune`
+    pub fn merge(&mut self, other: &Nets) {
         for edge in other.graph.all_edges() {
             let mut ew = edge.2.clone();
             ew.interactable = NetEdge::interactable(edge.0 .0, edge.1 .0);
             self.graph.add_edge(edge.0, edge.1, ew); // adding edges also add nodes if they do not already exist
         }
-        self.prune(extra_vertices);
     }
     /// deletes NetEdge e from self
     pub fn delete_edge(&mut self, e: &NetEdge) {
@@ -2,6 +2,7 @@
 //!
 //!
 
+use std::fmt;
 use std::rc::Rc;
 
 use petgraph::algo::tarjan_scc;
@@ -107,22 +108,73 @@ where
     }
 }
 
+/// more than one distinct user net label placed on a single connected net
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelConflict {
+    /// the conflicting net names, sorted regardless of case. The first is used as the net name.
+    pub names: Vec<Rc<String>>,
+}
+
+impl fmt::Display for LabelConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.names.iter().map(|n| n.as_str()).collect();
+        write!(f, "conflicting net labels: {}", names.join(", "))
+    }
+}
+
 /// finds an appropriate net name and assigns it to all edge in edges.
+/// user labels placed on any edge take precedence - subnets sharing a user label end up with the same net name.
+/// returns the net name, and the conflict if more than one distinct user label is placed on the subnet.
 fn unify_labels(
     nets: &mut Nets,
     edges: Vec<(NetVertex, NetVertex)>,
     taken_net_names: &[Rc<String>],
-) -> Rc<String> {
+    user_labels: &[(SSPoint, Rc<String>)],
+) -> (Rc<String>, Option<LabelConflict>) {
     let mut label = None;
-    // get smallest untaken of existing labels, if any
-    for tup in &edges {
-        if let Some(ew) = nets.graph.edge_weight(tup.0, tup.1) {
-            if let Some(label1) = &ew.label {
-                if taken_net_names.contains(label1) {
-                    continue;
-                }
-                if label.is_none() || label1 < label.as_ref().unwrap() {
-                    label = Some(label1.clone());
+    let mut conflict = None;
+    // user labels placed on this subnet, if any
+    let mut user_names: Vec<Rc<String>> = user_labels
+        .iter()
+        .filter(|(ssp, _)| {
+            edges.iter().any(|tup| {
+                nets.graph
+                    .edge_weight(tup.0, tup.1)
+                    .is_some_and(|ew| ew.interactable.contains_ssp(*ssp))
+            })
+        })
+        .map(|(_, name)| name.clone())
+        .collect();
+    // ngspice node names are case insensitive
+    user_names.sort_by(|a, b| {
+        a.to_ascii_lowercase()
+            .cmp(&b.to_ascii_lowercase())
+            .then_with(|| a.cmp(b))
+    });
+    user_names.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    if let Some(name) = user_names.first() {
+        label = Some(name.clone());
+        if user_names.len() > 1 {
+            conflict = Some(LabelConflict { names: user_names });
+        }
+    } else {
+        // get smallest untaken of existing labels, if any
+        for tup in &edges {
+            if let Some(ew) = nets.graph.edge_weight(tup.0, tup.1) {
+                if let Some(label1) = &ew.label {
+                    if taken_net_names
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(label1))
+                        || user_labels
+                            .iter()
+                            .any(|(_, name)| name.eq_ignore_ascii_case(label1))
+                    {
+                        // taken by another subnet, or reserved by a user label elsewhere
+                        continue;
+                    }
+                    if label.is_none() || label1 < label.as_ref().unwrap() {
+                        label = Some(label1.clone());
+                    }
                 }
             }
         }
@@ -137,12 +189,22 @@ fn unify_labels(
             ew.label = label.clone();
         }
     }
-    label.unwrap()
+    (label.unwrap(), conflict)
 }
 
 /// this function is called whenever schematic is changed. Ensures all connected nets have the same net name, overlapping segments are merged, etc.
 /// extra_vertices are coordinates where net segments should be bisected (device ports)
-pub fn prune(nets: &mut Nets, port_coords: &[SSPoint]) {
+/// user_labels are net labels placed in the schematic, which name the net they are placed on
+/// returns the subnets with conflicting user labels
+pub fn prune(
+    nets: &mut Nets,
+    port_coords: &[SSPoint],
+    user_labels: &[(SSPoint, Rc<String>)],
+) -> Vec<LabelConflict> {
+    // reserve the user labels currently placed so they are never auto generated
+    nets.label_manager
+        .reserve(user_labels.iter().map(|(_, name)| name));
+
     let net_vertices: Box<[SSPoint]> = nets.graph.nodes().map(|nv| nv.0).collect();
 
     // bisect/merge edges
@@ -162,8 +224,12 @@ pub fn prune(nets: &mut Nets, port_coords: &[SSPoint]) {
     // unify labels - give vector of taken labels
     let subgraph_vertices = tarjan_scc(&*nets.graph); // this finds the subnets
     let mut taken_net_names = vec![];
+    let mut conflicts = vec![];
     for vertices in subgraph_vertices {
         let edges = nets.nodes_to_edge_nodes(vertices);
-        taken_net_names.push(unify_labels(nets, edges, &taken_net_names));
+        let (label, conflict) = unify_labels(nets, edges, &taken_net_names, user_labels);
+        taken_net_names.push(label);
+        conflicts.extend(conflict);
     }
+    conflicts
 }
@@ -560,6 +560,15 @@ where
         self.tentative_by_vspoint(vsp, &mut skip);
         self.selskip = skip;
     }
+    /// replace the selection with elements, e.g. to highlight elements from outside the schematic
+    pub fn select(&mut self, elements: HashSet<E>) {
+        if elements.len() == 1 {
+            self.active_element = elements.iter().next().cloned();
+        } else {
+            self.active_element = None;
+        }
+        self.selected = elements;
+    }
     /// put every tentative element into selected
     fn tentatives_to_selected(&mut self) {
         self.selected = self.tentatives.clone();
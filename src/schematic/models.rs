@@ -2,7 +2,7 @@
 //!
 //!
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct NgModels {
     models: Vec<NgModel>,
 }
@@ -47,7 +47,7 @@ impl NgModels {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 struct NgModel {
     name: String,
     definition: String,
@@ -0,0 +1,88 @@
+//! Diagnostics
+//! finds the problems ngspice reports in its output, and the devices and nodes they name
+
+use super::OutputLine;
+
+/// words marking a line of ngspice output as a problem, if it is not already on stderr
+const PROBLEM_WORDS: [&str; 6] = [
+    "error",
+    "warning",
+    "singular",
+    "trouble",
+    "unknown",
+    "could not",
+];
+
+/// problem reported by ngspice
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// the line of output reporting the problem
+    pub message: String,
+    /// words of the message which may be device identifiers, e.g. `m1`
+    pub devices: Vec<String>,
+    /// node names of the message, e.g. `n1` in `check node n1`
+    pub nodes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// returns the diagnostic of line, or None if line does not report a problem
+    fn new(line: &OutputLine) -> Option<Self> {
+        let message = match line {
+            OutputLine::Stderr(s) => s,
+            OutputLine::Stdout(s) => {
+                let lower = s.to_ascii_lowercase();
+                if !PROBLEM_WORDS.iter().any(|w| lower.contains(w)) {
+                    return None;
+                }
+                s
+            }
+            OutputLine::Command(_) | OutputLine::Stat(_) => return None,
+        }
+        .trim();
+        if message.is_empty() {
+            return None;
+        }
+        let words: Vec<&str> = message
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .filter(|w| !w.is_empty())
+            .collect();
+        // nodes follow `node`, or `nodes` as a list joined by `and`, e.g. `check nodes n1 and n2`
+        let mut nodes: Vec<String> = vec![];
+        let mut expect_node = false;
+        // true after `nodes`, until a word other than a node or `and`
+        let mut node_list = false;
+        let mut last_node = false;
+        for w in &words {
+            let lower = w.to_ascii_lowercase();
+            if expect_node {
+                nodes.push(w.to_string());
+                expect_node = false;
+                last_node = true;
+                continue;
+            }
+            if lower == "node" || lower == "nodes" {
+                expect_node = true;
+                node_list = lower == "nodes";
+            } else if lower == "and" && node_list && last_node {
+                expect_node = true;
+            } else {
+                node_list = false;
+            }
+            last_node = false;
+        }
+        Some(Diagnostic {
+            message: message.to_string(),
+            devices: words
+                .iter()
+                .filter(|w| !nodes.iter().any(|n| n == *w))
+                .map(|w| w.to_string())
+                .collect(),
+            nodes,
+        })
+    }
+}
+
+/// returns the problems reported in output, in order
+pub fn diagnostics(output: &[OutputLine]) -> Vec<Diagnostic> {
+    output.iter().filter_map(Diagnostic::new).collect()
+}
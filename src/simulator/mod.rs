@@ -0,0 +1,90 @@
+//! Simulator
+//! backends which run ngspice simulations for the circuit schematic
+
+mod diagnostics;
+mod shared;
+mod subprocess;
+
+pub use diagnostics::diagnostics;
+pub use shared::SharedSimulator;
+pub use subprocess::SubprocessSimulator;
+
+use paprika::{PkVecvalues, PkVecvaluesall, SimData, SimPlot};
+
+use crate::preferences::{Preferences, SimulatorKind};
+
+/// simulator backend circe talks to
+pub trait Simulator {
+    /// load the netlist, given as lines including the analysis card and `.end`
+    fn load(&mut self, lines: &[String]) -> Result<(), String>;
+    /// start the loaded simulation in the background
+    fn run(&mut self) -> Result<(), String>;
+    /// halt the running simulation
+    fn halt(&mut self);
+    /// stop the loaded transient simulation at time (seconds). Returns false if the breakpoint was not set
+    fn set_breakpoint(&mut self, time: f64) -> bool;
+    /// continue the simulation stopped at a breakpoint. Returns false if it could not be continued
+    fn resume(&mut self) -> bool;
+    /// returns true while the simulation is running
+    fn is_running(&mut self) -> bool;
+    /// latest progress reported by the simulation, e.g. ("tran", 34.5)
+    fn progress(&self) -> Option<(String, f32)>;
+    /// results of the last simulation, up to where it stopped
+    fn results(&mut self) -> Result<SimPlot, String>;
+    /// every plot of the last simulation, oldest first, e.g. the spectrum and the integrated noise of a noise analysis
+    fn all_results(&mut self) -> Result<Vec<SimPlot>, String>;
+    /// send a command to the simulator, e.g. `show all`
+    fn command(&mut self, cmd: &str) -> Result<(), String>;
+    /// take the output produced since the last call, oldest first
+    fn take_output(&mut self) -> Vec<OutputLine>;
+}
+
+/// a line of simulator output, or of the console
+#[derive(Debug, Clone)]
+pub enum OutputLine {
+    /// command entered by the user
+    Command(String),
+    Stdout(String),
+    Stderr(String),
+    /// status, e.g. simulation progress
+    Stat(String),
+}
+
+/// create the simulator chosen in prefs, or return a message describing why simulation is disabled
+pub fn from_preferences(prefs: &Preferences) -> Result<Box<dyn Simulator>, String> {
+    match prefs.simulator {
+        SimulatorKind::Shared => {
+            SharedSimulator::new(prefs).map(|s| Box::new(s) as Box<dyn Simulator>)
+        }
+        SimulatorKind::Subprocess => {
+            SubprocessSimulator::new(prefs).map(|s| Box::new(s) as Box<dyn Simulator>)
+        }
+    }
+}
+
+/// returns the values of every vector of plot at point i, e.g. the operating point
+pub fn plot_values(plot: &SimPlot, i: usize) -> PkVecvaluesall {
+    let vecsa: Vec<Box<PkVecvalues>> = plot
+        .vecs
+        .iter()
+        .map(|v| {
+            let (creal, cimag, is_complex) = match &v.data {
+                SimData::Real(d) => (d.get(i).copied().unwrap_or_default(), 0.0, false),
+                SimData::Complex(d) => d.get(i).map_or((0.0, 0.0, true), |c| (c.re, c.im, true)),
+            };
+            Box::new(PkVecvalues {
+                name: v.name.clone(),
+                creal,
+                cimag,
+                is_scale: v.scale.is_none()
+                    && plot.vecs.iter().any(|o| o.scale.as_ref() == Some(&v.name)),
+                is_complex,
+            })
+        })
+        .collect();
+    PkVecvaluesall {
+        count: vecsa.len() as i32,
+        index: i as i32,
+        vecsa,
+    }
+}
@@ -0,0 +1,165 @@
+//! Shared library simulator
+//! runs ngspice in-process through paprika - fastest, but an ngspice crash takes circe down with it
+
+use std::sync::{Arc, Mutex};
+
+use paprika::*;
+
+use super::{OutputLine, Simulator};
+use crate::preferences::Preferences;
+
+/// Spice Manager to facillitate interaction with NgSpice
+#[derive(Debug, Default)]
+struct SpManager {
+    /// latest progress reported by ngspice, e.g. ("tran", 34.5)
+    progress: Option<(String, f32)>,
+    /// true while the ngspice background thread is running
+    bg_running: bool,
+    /// output not yet taken
+    output: Vec<OutputLine>,
+}
+
+#[allow(unused_variables)]
+impl paprika::PkSpiceManager for SpManager {
+    fn cb_send_char(&mut self, msg: String, id: i32) {
+        let opt = msg.split_once(' ');
+        let (token, msgs) = match opt {
+            Some(tup) => (tup.0, tup.1),
+            None => (msg.as_str(), msg.as_str()),
+        };
+        let line = match token {
+            "stdout" => OutputLine::Stdout(msgs.to_string()),
+            "stderr" => OutputLine::Stderr(msgs.to_string()),
+            _ => OutputLine::Stdout(msg.clone()),
+        };
+        self.output.push(line);
+    }
+    fn cb_send_stat(&mut self, msg: String, id: i32) {
+        if let Some((name, pct)) = parse_stat(&msg) {
+            self.progress = Some((name.to_string(), pct));
+        }
+        self.output.push(OutputLine::Stat(msg));
+    }
+    fn cb_ctrldexit(&mut self, status: i32, is_immediate: bool, is_quit: bool, id: i32) {}
+    fn cb_send_init(&mut self, pkvecinfoall: PkVecinfoall, id: i32) {}
+    fn cb_send_data(&mut self, pkvecvaluesall: PkVecvaluesall, count: i32, id: i32) {}
+    fn cb_bgt_state(&mut self, is_fin: bool, id: i32) {
+        self.bg_running = !is_fin;
+    }
+}
+
+/// ngspice shared library loaded in-process, simulating in the ngspice background thread
+pub struct SharedSimulator {
+    /// ngspice library
+    lib: PkSpice,
+    /// spice manager
+    /// locked by paprika for the duration of each callback - do not hold the lock while calling into lib
+    spmanager: Arc<Mutex<SpManager>>,
+    /// plots which existed before the last simulation was started
+    plots_before: Vec<String>,
+}
+
+impl SharedSimulator {
+    /// locate and load the ngspice shared library and the code models listed in prefs
+    pub fn new(prefs: &Preferences) -> Result<Self, String> {
+        let path = match locate_sharedspice(prefs.ngspice_path.as_deref()) {
+            Some(path) => path,
+            None => {
+                return Err(format!(
+                    "ngspice not found - simulation disabled. Install ngspice, or set {} or ngspice_path in {}",
+                    SHAREDSPICE_ENV,
+                    Preferences::path().map_or(String::from("the preferences"), |p| p
+                        .display()
+                        .to_string()),
+                ))
+            }
+        };
+        let mut lib: PkSpice =
+            PkSpice::new(path.as_os_str()).map_err(|e| format!("{} - simulation disabled", e))?;
+        let spmanager = Arc::new(Mutex::new(SpManager::default()));
+        lib.init_manager(spmanager.clone());
+        // simulations without the code models still work, so errors are reported in the console
+        if let Some(p) = &prefs.cm_input_path {
+            if let Err(e) = lib.cm_input_path(Some(&p.to_string_lossy())) {
+                let line = OutputLine::Stderr(format!("cm_input_path: {}", e));
+                spmanager.lock().unwrap().output.push(line);
+            }
+        }
+        for cm in &prefs.codemodels {
+            if let Err(e) = lib.load_codemodel(&cm.to_string_lossy()) {
+                let line = OutputLine::Stderr(format!("{}: {}", cm.display(), e));
+                spmanager.lock().unwrap().output.push(line);
+            }
+        }
+        Ok(SharedSimulator {
+            lib,
+            spmanager,
+            plots_before: vec![],
+        })
+    }
+}
+
+impl Simulator for SharedSimulator {
+    fn load(&mut self, lines: &[String]) -> Result<(), String> {
+        self.lib.circ(lines).map_err(|e| e.to_string())
+    }
+    fn run(&mut self) -> Result<(), String> {
+        self.plots_before = self.lib.get_all_plots();
+        {
+            let mut spmanager = self.spmanager.lock().unwrap();
+            spmanager.progress = None;
+            // set before the thread starts, so that polling does not finish the simulation early
+            spmanager.bg_running = true;
+        }
+        self.lib.bg_run().map_err(|e| {
+            self.spmanager.lock().unwrap().bg_running = false;
+            e.to_string()
+        })
+    }
+    fn halt(&mut self) {
+        // the thread has finished already if ngspice cannot halt it
+        let _ = self.lib.bg_halt();
+    }
+    fn set_breakpoint(&mut self, time: f64) -> bool {
+        self.lib.set_breakpoint(time).is_ok()
+    }
+    fn resume(&mut self) -> bool {
+        self.spmanager.lock().unwrap().bg_running = true;
+        let resumed = self.lib.bg_resume().is_ok();
+        if !resumed {
+            self.spmanager.lock().unwrap().bg_running = false;
+        }
+        resumed
+    }
+    fn is_running(&mut self) -> bool {
+        let bg_running = self.spmanager.lock().unwrap().bg_running;
+        bg_running || self.lib.is_running()
+    }
+    fn progress(&self) -> Option<(String, f32)> {
+        self.spmanager.lock().unwrap().progress.clone()
+    }
+    fn results(&mut self) -> Result<SimPlot, String> {
+        Ok(self.lib.get_plot(&self.lib.get_cur_plot()))
+    }
+    fn all_results(&mut self) -> Result<Vec<SimPlot>, String> {
+        // ngspice lists the newest plots first
+        Ok(self
+            .lib
+            .get_all_plots()
+            .iter()
+            .rev()
+            .filter(|p| !self.plots_before.contains(p))
+            .map(|p| self.lib.get_plot(p))
+            .collect())
+    }
+    fn command(&mut self, cmd: &str) -> Result<(), String> {
+        // ngspice expects the library to be unloaded on quit
+        if let Some("quit" | "exit") = cmd.split_whitespace().next() {
+            return Err(String::from("quit is not supported - close circe instead"));
+        }
+        self.lib.command(cmd).map_err(|e| e.to_string())
+    }
+    fn take_output(&mut self) -> Vec<OutputLine> {
+        std::mem::take(&mut self.spmanager.lock().unwrap().output)
+    }
+}
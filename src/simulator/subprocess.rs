@@ -0,0 +1,203 @@
+//! Subprocess simulator
+//! runs the ngspice executable in batch mode and reads its rawfile - an ngspice crash only fails the simulation
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use paprika::{read_rawfile, SimPlot, SimUnit};
+
+use super::{OutputLine, Simulator};
+use crate::preferences::Preferences;
+
+/// ngspice executable run once per simulation, as `ngspice -b -r <rawfile> -o <log> <netlist>`.
+/// Breakpoints and progress are not supported. Code models are those loaded by the ngspice installation's spinit.
+pub struct SubprocessSimulator {
+    /// ngspice executable
+    bin: PathBuf,
+    /// directory holding the netlist, rawfile and log of the simulation
+    dir: PathBuf,
+    /// loaded netlist
+    lines: Vec<String>,
+    /// running ngspice process, if any
+    child: Option<Child>,
+    /// error of the last run, if it failed
+    error: Option<String>,
+    /// output not yet taken
+    output: Vec<OutputLine>,
+}
+
+impl SubprocessSimulator {
+    /// ngspice executable from prefs, or `ngspice` from the search path
+    pub fn new(prefs: &Preferences) -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("circe-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        Ok(SubprocessSimulator {
+            bin: prefs
+                .ngspice_bin
+                .clone()
+                .unwrap_or_else(|| PathBuf::from("ngspice")),
+            dir,
+            lines: vec![],
+            child: None,
+            error: None,
+            output: vec![],
+        })
+    }
+    fn netlist_path(&self) -> PathBuf {
+        self.dir.join("circe.cir")
+    }
+    fn raw_path(&self) -> PathBuf {
+        self.dir.join("circe.raw")
+    }
+    fn log_path(&self) -> PathBuf {
+        self.dir.join("circe.log")
+    }
+    /// queue the ngspice log as output, error lines as stderr
+    fn take_log(&mut self) {
+        let log = std::fs::read_to_string(self.log_path()).unwrap_or_default();
+        self.output.extend(log.lines().map(|l| {
+            if l.to_ascii_lowercase().contains("error") {
+                OutputLine::Stderr(l.to_string())
+            } else {
+                OutputLine::Stdout(l.to_string())
+            }
+        }));
+    }
+    /// last error lines of the ngspice log
+    fn log_errors(&self) -> String {
+        std::fs::read_to_string(self.log_path())
+            .unwrap_or_default()
+            .lines()
+            .filter(|l| l.to_ascii_lowercase().contains("error"))
+            .last()
+            .unwrap_or("ngspice failed")
+            .trim()
+            .to_string()
+    }
+}
+
+impl Simulator for SubprocessSimulator {
+    fn load(&mut self, lines: &[String]) -> Result<(), String> {
+        self.lines = lines.to_vec();
+        Ok(())
+    }
+    fn run(&mut self) -> Result<(), String> {
+        self.error = None;
+        let _ = std::fs::remove_file(self.raw_path());
+        std::fs::write(self.netlist_path(), self.lines.join("\n") + "\n")
+            .map_err(|e| format!("{}: {}", self.netlist_path().display(), e))?;
+        let child = Command::new(&self.bin)
+            .arg("-b")
+            .arg("-r")
+            .arg(self.raw_path())
+            .arg("-o")
+            .arg(self.log_path())
+            .arg(self.netlist_path())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("could not run {}: {}", self.bin.display(), e))?;
+        self.child = Some(child);
+        Ok(())
+    }
+    fn halt(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    fn set_breakpoint(&mut self, _time: f64) -> bool {
+        false
+    }
+    fn resume(&mut self) -> bool {
+        false
+    }
+    fn is_running(&mut self) -> bool {
+        let status = match &mut self.child {
+            Some(child) => child.try_wait(),
+            None => return false,
+        };
+        match status {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                self.child = None;
+                self.take_log();
+                if !status.success() {
+                    self.error = Some(self.log_errors());
+                }
+                false
+            }
+            Err(e) => {
+                self.child = None;
+                self.error = Some(e.to_string());
+                false
+            }
+        }
+    }
+    fn progress(&self) -> Option<(String, f32)> {
+        None
+    }
+    fn results(&mut self) -> Result<SimPlot, String> {
+        self.all_results()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| self.log_errors())
+    }
+    fn all_results(&mut self) -> Result<Vec<SimPlot>, String> {
+        if let Some(e) = &self.error {
+            return Err(e.clone());
+        }
+        let mut plots = read_rawfile(&self.raw_path())
+            .map_err(|e| format!("{}: {}", self.raw_path().display(), e))?;
+        // rawfiles name vectors as v(node) and i(source) - use the names of the shared library instead
+        // scales (time, frequency, sweep) are named alike, and the vectors refer to them by their new names
+        for plot in &mut plots {
+            let names: HashMap<String, String> = plot
+                .vecs
+                .iter()
+                .map(|v| (v.name.clone(), vector_name(&v.name, v.unit)))
+                .collect();
+            for v in &mut plot.vecs {
+                v.name = vector_name(&v.name, v.unit);
+                if let Some(scale) = &mut v.scale {
+                    if let Some(name) = names.get(scale.as_str()) {
+                        *scale = name.clone();
+                    }
+                }
+            }
+        }
+        Ok(plots)
+    }
+    fn command(&mut self, _cmd: &str) -> Result<(), String> {
+        Err(String::from(
+            "commands are not supported by the subprocess simulator - set simulator to shared in the preferences",
+        ))
+    }
+    fn take_output(&mut self) -> Vec<OutputLine> {
+        std::mem::take(&mut self.output)
+    }
+}
+
+impl Drop for SubprocessSimulator {
+    fn drop(&mut self) {
+        self.halt();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// returns the name of a rawfile vector as named by the shared library, e.g. `out` for `v(out)` and `v1#branch` for `i(v1)`
+fn vector_name(name: &str, unit: SimUnit) -> String {
+    let inner = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|s| s.strip_suffix(')'))
+            .map(String::from)
+    };
+    match unit {
+        SimUnit::Voltage => inner("v("),
+        SimUnit::Current => inner("i(").map(|s| format!("{}#branch", s)),
+        _ => None,
+    }
+    .unwrap_or_else(|| name.to_string())
+}